@@ -0,0 +1,237 @@
+use crate::error::edit_distance;
+use crate::options::Options;
+
+use std::ops::Range;
+use toml_edit::{ImDocument, Item, Key, TableLike};
+use ttyper::{
+    config::{Config, Theme},
+    keys::KeyBindings,
+};
+
+// Greatest edit distance from an unknown key to a known one for it to be suggested
+const MAX_SUGGESTION_DISTANCE: usize = 3;
+
+/// A problem found in a config file
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    /// Byte range of the text with the problem
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+/// Settings which a table of a config file can contain
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scope {
+    /// The top level of a config file
+    Config,
+    /// A `[profile.<name>]` table, which can contain anything but profiles
+    Profile,
+    /// A `[theme]` table, or a theme file
+    Theme,
+    /// A `[keys]` table
+    Keys,
+}
+
+impl Scope {
+    /// Keys which can be set in a table of this scope
+    fn keys(self) -> Vec<String> {
+        let keys = match self {
+            Scope::Config | Scope::Profile => {
+                let mut keys = table_keys(Config::default());
+                keys.extend(Options::KEYS.iter().map(|&key| key.to_owned()));
+                keys
+            }
+            Scope::Theme => {
+                let mut keys = table_keys(Theme::default());
+                keys.push(String::from("name"));
+                keys
+            }
+            Scope::Keys => table_keys(KeyBindings::default()),
+        };
+        keys.into_iter()
+            .filter(|key| self != Scope::Profile || key != "profile")
+            .collect()
+    }
+
+    /// Dotted keys of the table of this scope, for checking values on their own
+    fn prefix(self) -> &'static str {
+        match self {
+            Scope::Config | Scope::Profile => "",
+            Scope::Theme => "theme.",
+            Scope::Keys => "keys.",
+        }
+    }
+}
+
+/// Find every problem in a config file, rather than just the first like loading it does
+pub fn config(text: &str) -> Vec<Problem> {
+    check(text, Scope::Config)
+}
+
+/// Find every problem in a theme file
+pub fn theme(text: &str) -> Vec<Problem> {
+    check(text, Scope::Theme)
+}
+
+fn check(text: &str, scope: Scope) -> Vec<Problem> {
+    match ImDocument::parse(text) {
+        Ok(document) => {
+            let mut problems = Vec::new();
+            check_table(document.as_table(), scope, &mut problems);
+            problems.sort_by_key(|problem| problem.span.start);
+            problems
+        }
+        Err(err) => vec![Problem {
+            span: err.span().unwrap_or(0..0),
+            message: err.message().to_owned(),
+        }],
+    }
+}
+
+fn check_table(table: &dyn TableLike, scope: Scope, problems: &mut Vec<Problem>) {
+    let keys = scope.keys();
+    for (name, item) in table.iter() {
+        let key = table.key(name);
+        let key_span = key.and_then(Key::span).unwrap_or(0..0);
+        let value_span = item.span().unwrap_or_else(|| key_span.clone());
+
+        if !keys.iter().any(|key| key == name) {
+            let mut message = format!("unknown key `{}`", name);
+            if let Some(nearest) = keys
+                .iter()
+                .min_by_key(|key| edit_distance(name, key))
+                .filter(|key| edit_distance(name, key) <= MAX_SUGGESTION_DISTANCE)
+            {
+                message.push_str(&format!(", did you mean `{}`?", nearest));
+            }
+            problems.push(Problem {
+                span: key_span,
+                message,
+            });
+            continue;
+        }
+
+        match (scope, name, item.as_table_like()) {
+            (Scope::Config, "profile", Some(profiles)) => {
+                for (profile_name, profile) in profiles.iter() {
+                    match profile.as_table_like() {
+                        Some(profile) => check_table(profile, Scope::Profile, problems),
+                        None => problems.push(Problem {
+                            span: profile.span().unwrap_or_else(|| value_span.clone()),
+                            message: format!("profile `{}` should be a table", profile_name),
+                        }),
+                    }
+                }
+            }
+            (Scope::Config | Scope::Profile, "theme", Some(theme)) => {
+                check_table(theme, Scope::Theme, problems)
+            }
+            (Scope::Config | Scope::Profile, "keys", Some(keys)) => {
+                check_table(keys, Scope::Keys, problems)
+            }
+            (_, _, _) => match item {
+                Item::Value(value) => {
+                    // check values one at a time, so a problem doesn't hide the ones after it
+                    let text = format!(
+                        "{}{} = {}",
+                        scope.prefix(),
+                        Key::new(name).display_repr(),
+                        value.to_string().trim()
+                    );
+                    let options = matches!(scope, Scope::Config | Scope::Profile);
+                    let result = if options && Options::KEYS.contains(&name) {
+                        toml::from_str::<Options>(&text).map(drop)
+                    } else {
+                        toml::from_str::<Config>(&text).map(drop)
+                    };
+                    if let Err(err) = result {
+                        problems.push(Problem {
+                            span: value_span,
+                            message: err.message().to_owned(),
+                        });
+                    }
+                }
+                _ => problems.push(Problem {
+                    span: value_span,
+                    message: format!("`{}` should be a value, not a table", name),
+                }),
+            },
+        }
+    }
+}
+
+/// Keys of a struct's serialized table
+fn table_keys(value: impl serde::Serialize) -> Vec<String> {
+    toml::Table::try_from(value)
+        .map(|table| table.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(text: &str) -> Vec<(usize, String)> {
+        config(text)
+            .into_iter()
+            .map(|problem| (problem.span.start, problem.message))
+            .collect()
+    }
+
+    #[test]
+    fn finds_every_problem() {
+        let text = "\
+words = 0
+show_hud = true
+typo_key = 1
+
+[theme]
+prompt_corect = \"red\"
+title = \"redd\"
+border_type = \"wavy\"
+prompt_untyped = \"gray;blinking\"
+
+[keys]
+restart = \"ctrl-n\"
+quit = [\"q\", \"hyper-q\"]
+delete_word = \"ctrl-w\"
+
+[profile.exam]
+time = 60
+no_backspace = \"yes\"
+[profile.exam.theme]
+title = \"blue\"
+";
+        let problems = messages(text);
+        let found = |offset: &str, message: &str| {
+            let start = text.find(offset).unwrap();
+            assert!(
+                problems
+                    .iter()
+                    .any(|(at, found)| *at == start && found.contains(message)),
+                "expected `{}` at `{}` in {:?}",
+                message,
+                offset,
+                problems
+            );
+        };
+
+        assert_eq!(problems.len(), 9);
+        found("0\n", "nonzero");
+        found("typo_key", "unknown key `typo_key`");
+        found("prompt_corect", "did you mean `prompt_correct`?");
+        found("\"redd\"", "color");
+        found("\"wavy\"", "border type");
+        found("\"gray;blinking\"", "style modifier");
+        found("\"yes\"", "bool");
+        found("[\"q\"", "unknown modifier `hyper`");
+        found("delete_word", "did you mean `delete-word`?");
+    }
+
+    #[test]
+    fn accepts_valid_configs() {
+        assert_eq!(config("theme = \"nord\"\nlanguage = \"german\"\n"), []);
+        assert_eq!(theme("title = \"red;bold\"\nborder_type = \"plain\"\n"), []);
+        assert_eq!(config("[theme\n").len(), 1);
+    }
+}
@@ -1,87 +1,318 @@
+use crate::keys::KeyBindings;
+
 use ratatui::{
     style::{Color, Modifier, Style},
     widgets::BorderType,
 };
 use serde::{
     de::{self, IntoDeserializer},
-    Deserialize,
+    ser, Deserialize, Serialize,
 };
+use std::collections::BTreeMap;
 
-#[derive(Debug, Deserialize)]
+/// Contents of the config file, where missing settings take their default values
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
+    /// Language used when one isn't specified
     pub default_language: String,
+    /// Show live statistics above the test
+    pub show_hud: bool,
+    /// Show a keyboard below the test which highlights the next key
+    pub show_keyboard: bool,
+    /// Layout of the on-screen keyboard
+    pub keyboard_layout: KeyboardLayout,
+    /// Styles of the interface, or the name of a preset theme
+    #[serde(deserialize_with = "deserialize_theme")]
     pub theme: Theme,
+    /// Keys bound to the actions of the test and results screens
+    pub keys: KeyBindings,
+    /// Named sets of settings selected with `--profile`, which override the rest of the config
+    pub profile: BTreeMap<String, toml::Table>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             default_language: "english200".into(),
+            show_hud: true,
+            show_keyboard: false,
+            keyboard_layout: KeyboardLayout::default(),
             theme: Theme::default(),
+            keys: KeyBindings::default(),
+            profile: BTreeMap::new(),
+        }
+    }
+}
+
+/// Physical key arrangement of the on-screen keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyboardLayout {
+    /// US QWERTY
+    #[default]
+    Qwerty,
+    /// US Dvorak
+    Dvorak,
+    /// Colemak
+    Colemak,
+}
+
+impl KeyboardLayout {
+    /// Unshifted characters of each row, from the number row down
+    pub fn rows(self) -> [&'static str; 4] {
+        match self {
+            Self::Qwerty => [
+                "`1234567890-=",
+                "qwertyuiop[]\\",
+                "asdfghjkl;'",
+                "zxcvbnm,./",
+            ],
+            Self::Dvorak => [
+                "`1234567890[]",
+                "',.pyfgcrl/=\\",
+                "aoeuidhtns-",
+                ";qjkxbmwvz",
+            ],
+            Self::Colemak => [
+                "`1234567890-=",
+                "qwfpgjluy;[]\\",
+                "arstdhneio'",
+                "zxcvbkm,./",
+            ],
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// Styles of the interface, deserialized from strings like `"blue:white;italic"`
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Theme {
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Name of the preset theme these styles override, if any. The preset's styles are loaded
+    /// by the application, since presets can be installed in the config directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Default style, which includes empty cells
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub default: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Title text of boxes
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub title: Style,
 
     // test widget
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Border of the input box
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub input_border: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Border of the prompt box
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub prompt_border: Style,
 
-    #[serde(deserialize_with = "deserialize_border_type")]
+    /// Border type of all boxes
+    #[serde(
+        serialize_with = "serialize_border_type",
+        deserialize_with = "deserialize_border_type"
+    )]
     pub border_type: BorderType,
 
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Correctly typed words
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub prompt_correct: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Incorrectly typed words
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub prompt_incorrect: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Untyped words
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub prompt_untyped: Style,
 
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Correctly typed letters in the current word
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub prompt_current_correct: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Incorrectly typed letters in the current word
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub prompt_current_incorrect: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Untyped letters in the current word
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub prompt_current_untyped: Style,
 
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Cursor character
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub prompt_cursor: Style,
+    /// Position of the ghost being raced
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub prompt_ghost: Style,
+
+    /// Live statistics labels
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub hud_label: Style,
+    /// Live statistics values
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub hud_value: Style,
+
+    /// Border of the on-screen keyboard
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub keyboard_border: Style,
+    /// Keys without any results
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub keyboard_key: Style,
+    /// Next key to press during a test
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub keyboard_next_key: Style,
+    /// Keys typed well in the results heatmap
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub keyboard_good: Style,
+    /// Keys typed okay in the results heatmap
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub keyboard_fair: Style,
+    /// Keys typed poorly in the results heatmap
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub keyboard_poor: Style,
+
+    // race widgets
+    /// Border of the race board and scoreboard
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub race_border: Style,
+    /// Text of the race board and scoreboard
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub race_text: Style,
+    /// Progress bars of the race board
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub race_bar: Style,
+    /// Row of the local player, on top of the other race styles
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
+    pub race_you: Style,
 
     // results widget
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Overview text
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub results_overview: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Border of the overview
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub results_overview_border: Style,
 
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Worst keys text
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub results_worst_keys: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Border of the worst keys
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub results_worst_keys_border: Style,
 
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Results chart default, which includes plotted data
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub results_chart: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Results chart x-axis label
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub results_chart_x: Style,
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Results chart y-axis label
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub results_chart_y: Style,
 
-    #[serde(deserialize_with = "deserialize_style")]
+    /// Restart and quit prompt of the results
+    #[serde(
+        serialize_with = "serialize_style",
+        deserialize_with = "deserialize_style"
+    )]
     pub results_restart_prompt: Style,
 }
 
 impl Default for Theme {
     fn default() -> Self {
         Self {
+            name: None,
+
             default: Style::default(),
 
             title: Style::default()
@@ -106,6 +337,29 @@ impl Default for Theme {
                 .add_modifier(Modifier::BOLD),
 
             prompt_cursor: Style::default().add_modifier(Modifier::UNDERLINED),
+            prompt_ghost: Style::default().fg(Color::Black).bg(Color::Magenta),
+
+            hud_label: Style::default().fg(Color::Gray),
+            hud_value: Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+
+            keyboard_border: Style::default().fg(Color::Cyan),
+            keyboard_key: Style::default().fg(Color::Gray),
+            keyboard_next_key: Style::default()
+                .fg(Color::Black)
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            keyboard_good: Style::default().fg(Color::Black).bg(Color::Green),
+            keyboard_fair: Style::default().fg(Color::Black).bg(Color::Yellow),
+            keyboard_poor: Style::default().fg(Color::Black).bg(Color::Red),
+
+            race_border: Style::default().fg(Color::Cyan),
+            race_text: Style::default().fg(Color::Gray),
+            race_bar: Style::default().fg(Color::Green),
+            race_you: Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
 
             results_overview: Style::default()
                 .fg(Color::Cyan)
@@ -130,7 +384,37 @@ impl Default for Theme {
     }
 }
 
-fn deserialize_style<'de, D>(deserializer: D) -> Result<Style, D::Error>
+/// Deserialize a theme from a table of styles, or a string naming a preset theme
+fn deserialize_theme<'de, D>(deserializer: D) -> Result<Theme, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    struct ThemeVisitor;
+    impl<'de> de::Visitor<'de> for ThemeVisitor {
+        type Value = Theme;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a table of styles or the name of a theme")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            Ok(Theme {
+                name: Some(value.to_owned()),
+                ..Theme::default()
+            })
+        }
+
+        fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            Theme::deserialize(de::value::MapAccessDeserializer::new(map))
+        }
+    }
+
+    deserializer.deserialize_any(ThemeVisitor)
+}
+
+/// Deserialize a style from a string of colors and modifiers, e.g. `"blue:white;italic"`. For use
+/// with `#[serde(deserialize_with = "...")]`.
+pub fn deserialize_style<'de, D>(deserializer: D) -> Result<Style, D::Error>
 where
     D: de::Deserializer<'de>,
 {
@@ -185,7 +469,9 @@ where
     deserializer.deserialize_str(StyleVisitor)
 }
 
-fn deserialize_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
+/// Deserialize a color from a terminal color name like `"lightblue"`, a six digit hex code like
+/// `"00ff00"`, or `"reset"`
+pub fn deserialize_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
 where
     D: de::Deserializer<'de>,
 {
@@ -239,7 +525,8 @@ where
     deserializer.deserialize_str(ColorVisitor)
 }
 
-fn deserialize_border_type<'de, D>(deserializer: D) -> Result<BorderType, D::Error>
+/// Deserialize a border type from its lowercase name, e.g. `"rounded"`
+pub fn deserialize_border_type<'de, D>(deserializer: D) -> Result<BorderType, D::Error>
 where
     D: de::Deserializer<'de>,
 {
@@ -270,6 +557,93 @@ where
     deserializer.deserialize_str(BorderTypeVisitor)
 }
 
+/// Serialize a style to the string it's deserialized from, e.g. `"blue:white;italic"`. For use
+/// with `#[serde(serialize_with = "...")]`.
+pub fn serialize_style<S: ser::Serializer>(
+    style: &Style,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let color = |color: Option<Color>| match color {
+        Some(color) => color_name(color).ok_or_else(|| {
+            ser::Error::custom(format!("color {:?} can't be written in a config", color))
+        }),
+        None => Ok(String::from("none")),
+    };
+
+    let mut string = format!("{}:{}", color(style.fg)?, color(style.bg)?);
+    for (modifier, name) in [
+        (Modifier::BOLD, "bold"),
+        (Modifier::CROSSED_OUT, "crossed_out"),
+        (Modifier::DIM, "dim"),
+        (Modifier::HIDDEN, "hidden"),
+        (Modifier::ITALIC, "italic"),
+        (Modifier::RAPID_BLINK, "rapid_blink"),
+        (Modifier::SLOW_BLINK, "slow_blink"),
+        (Modifier::REVERSED, "reversed"),
+        (Modifier::UNDERLINED, "underlined"),
+    ] {
+        if style.add_modifier.contains(modifier) {
+            string.push(';');
+            string.push_str(name);
+        }
+    }
+    serializer.serialize_str(&string)
+}
+
+/// Serialize a color to the name or hex code it's deserialized from
+pub fn serialize_color<S: ser::Serializer>(
+    color: &Color,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match color_name(*color) {
+        Some(name) => serializer.serialize_str(&name),
+        None => Err(ser::Error::custom(format!(
+            "color {:?} can't be written in a config",
+            color
+        ))),
+    }
+}
+
+/// Name or hex code of a color, if it's one which can be deserialized
+fn color_name(color: Color) -> Option<String> {
+    Some(String::from(match color {
+        Color::Reset => "reset",
+        Color::Black => "black",
+        Color::White => "white",
+        Color::Red => "red",
+        Color::Green => "green",
+        Color::Yellow => "yellow",
+        Color::Blue => "blue",
+        Color::Magenta => "magenta",
+        Color::Cyan => "cyan",
+        Color::Gray => "gray",
+        Color::DarkGray => "darkgray",
+        Color::LightRed => "lightred",
+        Color::LightGreen => "lightgreen",
+        Color::LightYellow => "lightyellow",
+        Color::LightBlue => "lightblue",
+        Color::LightMagenta => "lightmagenta",
+        Color::LightCyan => "lightcyan",
+        Color::Rgb(r, g, b) => return Some(format!("{:02x}{:02x}{:02x}", r, g, b)),
+        Color::Indexed(_) => return None,
+    }))
+}
+
+/// Serialize a border type to its lowercase name, e.g. `"rounded"`
+pub fn serialize_border_type<S: ser::Serializer>(
+    border_type: &BorderType,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(match border_type {
+        BorderType::Plain => "plain",
+        BorderType::Rounded => "rounded",
+        BorderType::Double => "double",
+        BorderType::Thick => "thick",
+        BorderType::QuadrantInside => "quadrantinside",
+        BorderType::QuadrantOutside => "quadrantoutside",
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -347,4 +721,28 @@ mod tests {
         assert_eq!(border_type("quadrantinside"), BorderType::QuadrantInside);
         assert_eq!(border_type("quadrantoutside"), BorderType::QuadrantOutside);
     }
+
+    #[test]
+    fn serializes_what_it_deserializes() {
+        let text = toml::to_string(&Config::default()).expect("failed to serialize config");
+        let config: Config = toml::from_str(&text).expect("failed to deserialize config");
+        assert_eq!(config.theme.title, Theme::default().title);
+        assert_eq!(config.theme.keyboard_good, Theme::default().keyboard_good);
+        assert!(text.contains("prompt_current_correct = \"green:none;bold\""));
+
+        let config: Config = toml::from_str("theme = \"nord\"").unwrap();
+        assert_eq!(config.theme.name.as_deref(), Some("nord"));
+        let config: Config = toml::from_str("[theme]\nname = \"nord\"\ntitle = \"red\"").unwrap();
+        assert_eq!(config.theme.name.as_deref(), Some("nord"));
+        assert_eq!(config.theme.title, Style::default().fg(Color::Red));
+
+        let style = Style::default()
+            .fg(Color::Rgb(0, 0xff, 0))
+            .add_modifier(Modifier::DIM)
+            .add_modifier(Modifier::ITALIC);
+        assert_eq!(
+            serialize_style(&style, serde_json::value::Serializer).unwrap(),
+            "00ff00:none;dim;italic"
+        );
+    }
 }
@@ -0,0 +1,303 @@
+use std::{fmt, io, path::PathBuf};
+
+// Number of similarly named languages suggested when one can't be found
+const SUGGESTIONS: usize = 3;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The terminal couldn't be set up, drawn to or read from
+    Terminal(io::Error),
+    /// The platform has no config directory
+    NoConfigDir,
+    /// The config file couldn't be parsed
+    Config {
+        path: PathBuf,
+        position: Option<(usize, usize)>,
+        message: String,
+    },
+    /// A file couldn't be read
+    Io { path: PathBuf, source: io::Error },
+    /// No words, quotes or code snippets exist for a language
+    UnknownLanguage {
+        name: String,
+        kind: &'static str,
+        suggestions: Vec<String>,
+    },
+    /// No preset or installed theme or keyboard layout has the given name
+    Unknown {
+        kind: &'static str,
+        name: String,
+        suggestions: Vec<String>,
+    },
+    /// Problems were found when checking the config
+    InvalidConfig { problems: usize },
+    /// The profile selected with --profile isn't in the config
+    UnknownProfile {
+        name: String,
+        available: Vec<String>,
+    },
+    /// No quote matched the requested length or id
+    NoMatchingQuote { language: String },
+    /// A file wasn't valid UTF-8
+    Encoding { path: PathBuf },
+    /// A quote, code snippet or recording file couldn't be parsed
+    Parse {
+        path: PathBuf,
+        position: Option<(usize, usize)>,
+        message: String,
+    },
+    /// The test would have no words to type
+    EmptyContents,
+    /// A race couldn't be hosted or joined, or the connection to its host was lost
+    Network { addr: String, source: io::Error },
+}
+
+impl Error {
+    /// Error for a TOML file which couldn't be parsed, locating it by line and column
+    pub fn config(path: PathBuf, text: &str, err: toml::de::Error) -> Self {
+        Error::Config {
+            path,
+            position: err.span().map(|span| line_column(text, span.start)),
+            message: err.message().to_owned(),
+        }
+    }
+
+    /// Error for a quote or code snippet file which couldn't be parsed
+    pub fn parse(path: PathBuf, text: &str, err: toml::de::Error) -> Self {
+        Error::Parse {
+            path,
+            position: err.span().map(|span| line_column(text, span.start)),
+            message: err.message().to_owned(),
+        }
+    }
+
+    /// Error for a JSON file which couldn't be parsed, like a recording
+    pub fn json(path: PathBuf, err: serde_json::Error) -> Self {
+        Error::Parse {
+            path,
+            position: (err.line() > 0).then(|| (err.line(), err.column())),
+            // the position is reported separately, so drop it from serde_json's message
+            message: match err.to_string().rsplit_once(" at line ") {
+                Some((message, _)) => message.to_owned(),
+                None => err.to_string(),
+            },
+        }
+    }
+
+    /// Error for a missing language, suggesting the available ones with the closest names
+    pub fn unknown_language(
+        name: &str,
+        kind: &'static str,
+        available: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Error::UnknownLanguage {
+            name: name.to_owned(),
+            kind,
+            suggestions: nearest(name, available),
+        }
+    }
+
+    /// Error for a missing theme or keyboard layout, suggesting the available ones with the
+    /// closest names
+    pub fn unknown(
+        kind: &'static str,
+        name: &str,
+        available: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Error::Unknown {
+            kind,
+            name: name.to_owned(),
+            suggestions: nearest(name, available),
+        }
+    }
+
+    /// Process exit code, distinguishing the kinds of failure. 2 is used by clap for invalid
+    /// arguments and 101 by panics.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Terminal(_) => 1,
+            Error::NoConfigDir
+            | Error::Config { .. }
+            | Error::InvalidConfig { .. }
+            | Error::UnknownProfile { .. } => 3,
+            Error::Io { .. }
+            | Error::UnknownLanguage { .. }
+            | Error::Unknown { .. }
+            | Error::NoMatchingQuote { .. } => 4,
+            Error::Encoding { .. } | Error::Parse { .. } | Error::EmptyContents => 5,
+            Error::Network { .. } => 6,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Terminal(err) => write!(f, "terminal error: {}", err),
+            Error::NoConfigDir => write!(f, "couldn't find the config directory"),
+            Error::Config {
+                path,
+                position,
+                message,
+            } => {
+                write!(f, "invalid config in {}", path.display())?;
+                write_position(f, *position)?;
+                write!(f, ": {}", message)
+            }
+            Error::Io { path, source } => write!(f, "couldn't read {}: {}", path.display(), source),
+            Error::UnknownLanguage {
+                name,
+                kind,
+                suggestions,
+            } => {
+                write!(f, "no {} found for language `{}`", kind, name)?;
+                if !suggestions.is_empty() {
+                    write!(f, "\n  nearest languages: {}", suggestions.join(", "))?;
+                }
+                write!(f, "\n  run `ttyper --list-languages` to see all languages")
+            }
+            Error::InvalidConfig { problems: 1 } => write!(f, "found a problem in the config"),
+            Error::InvalidConfig { problems } => {
+                write!(f, "found {} problems in the config", problems)
+            }
+            Error::Unknown {
+                kind,
+                name,
+                suggestions,
+            } => {
+                write!(f, "no {} found named `{}`", kind, name)?;
+                if !suggestions.is_empty() {
+                    write!(f, "\n  nearest {}s: {}", kind, suggestions.join(", "))?;
+                }
+                write!(f, "\n  run `ttyper --list-{}s` to see all {}s", kind, kind)
+            }
+            Error::UnknownProfile { name, available } => {
+                write!(f, "no profile `{}` found in the config", name)?;
+                if !available.is_empty() {
+                    write!(f, "\n  available profiles: {}", available.join(", "))?;
+                }
+                Ok(())
+            }
+            Error::NoMatchingQuote { language } => {
+                write!(f, "no matching quote found for language `{}`", language)
+            }
+            Error::Encoding { path } => write!(f, "{} is not valid UTF-8", path.display()),
+            Error::Parse {
+                path,
+                position,
+                message,
+            } => {
+                write!(f, "couldn't parse {}", path.display())?;
+                write_position(f, *position)?;
+                write!(f, ": {}", message)
+            }
+            Error::EmptyContents => write!(f, "test contents are empty"),
+            Error::Network { addr, source } => write!(f, "network error with {}: {}", addr, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Terminal(err)
+            | Error::Io { source: err, .. }
+            | Error::Network { source: err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Terminal(err)
+    }
+}
+
+fn write_position(f: &mut fmt::Formatter<'_>, position: Option<(usize, usize)>) -> fmt::Result {
+    match position {
+        Some((line, column)) => write!(f, " at line {}, column {}", line, column),
+        None => Ok(()),
+    }
+}
+
+/// One-based line and column of a byte offset into text
+pub fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Names closest to the given one, for suggesting alternatives to a name which wasn't found
+fn nearest(name: &str, available: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut available: Vec<(usize, String)> = available
+        .into_iter()
+        .map(|candidate| (edit_distance(name, &candidate), candidate))
+        .collect();
+    available.sort();
+    available.dedup();
+
+    available
+        .into_iter()
+        .take(SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Levenshtein distance between two strings, in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_toml_errors() {
+        let text = "default_language = \"english\"\nshow_hud = 3\n";
+        let err = toml::from_str::<ttyper::config::Config>(text).unwrap_err();
+
+        match Error::config(PathBuf::from("config.toml"), text, err) {
+            Error::Config { position, .. } => assert_eq!(position, Some((2, 12))),
+            _ => panic!("expected a config error"),
+        }
+        assert_eq!(line_column(text, 0), (1, 1));
+    }
+
+    #[test]
+    fn suggests_nearest_languages() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+
+        let err = Error::unknown_language(
+            "englsh",
+            "words",
+            ["german", "english", "english1000", "spanish"].map(String::from),
+        );
+        match err {
+            Error::UnknownLanguage { suggestions, .. } => {
+                assert_eq!(suggestions, ["english", "english1000", "spanish"])
+            }
+            _ => panic!("expected an unknown language error"),
+        }
+    }
+}
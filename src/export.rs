@@ -0,0 +1,195 @@
+use ttyper::test::results::Results;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    /// Guess the format from a file extension, defaulting to JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Self::Csv,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    raw_wpm: f64,
+    adjusted_wpm: f64,
+    overall_accuracy: f64,
+    #[serde(flatten)]
+    results: &'a Results,
+}
+
+/// Write results to a file in the given format
+pub fn write_to_path(results: &Results, format: Format, path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    write(results, format, &mut file)?;
+    file.flush()
+}
+
+pub fn write<W: Write>(results: &Results, format: Format, writer: &mut W) -> io::Result<()> {
+    match format {
+        Format::Json => {
+            let export = JsonExport {
+                raw_wpm: results.raw_wpm(),
+                adjusted_wpm: results.adjusted_wpm(),
+                overall_accuracy: f64::from(results.accuracy.overall),
+                results,
+            };
+            serde_json::to_writer_pretty(&mut *writer, &export)?;
+            writeln!(writer)
+        }
+        Format::Csv => write_csv(results, writer),
+    }
+}
+
+/// Write results as CSV in a long `metric,key,value` format, which is easy to load into
+/// spreadsheets and dashboards
+fn write_csv<W: Write>(results: &Results, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "metric,key,value")?;
+    writeln!(writer, "raw_wpm,,{}", results.raw_wpm())?;
+    writeln!(writer, "adjusted_wpm,,{}", results.adjusted_wpm())?;
+    writeln!(writer, "accuracy,,{}", f64::from(results.accuracy.overall))?;
+    writeln!(
+        writer,
+        "correct_keypresses,,{}",
+        results.accuracy.overall.numerator
+    )?;
+    writeln!(
+        writer,
+        "total_keypresses,,{}",
+        results.accuracy.overall.denominator
+    )?;
+
+    // keys with the same name are merged, so each has one row
+    for (key, acc) in results.accuracy.per_key_name() {
+        writeln!(
+            writer,
+            "key_accuracy,{},{}",
+            csv_field(&key),
+            f64::from(acc)
+        )?;
+    }
+    for (key, latency) in results.timing.per_key_name() {
+        writeln!(writer, "key_latency,{},{}", csv_field(&key), latency)?;
+    }
+
+    let mut bigrams: Vec<(String, f64, f64)> = results
+        .bigrams
+        .iter()
+        .map(|((a, b), stats)| {
+            (
+                format!("{}{}", a, b),
+                stats.latency,
+                f64::from(stats.accuracy),
+            )
+        })
+        .collect();
+    bigrams.sort_by(|a, b| a.0.cmp(&b.0));
+    for (bigram, latency, _) in &bigrams {
+        writeln!(writer, "bigram_latency,{},{}", csv_field(bigram), latency)?;
+    }
+    for (bigram, _, acc) in &bigrams {
+        writeln!(writer, "bigram_accuracy,{},{}", csv_field(bigram), acc)?;
+    }
+
+    for word in &results.missed_words {
+        writeln!(writer, "missed_word,,{}", csv_field(word))?;
+    }
+
+    Ok(())
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent};
+    use std::time::{Duration, Instant};
+    use ttyper::test::Test;
+
+    /// Results of typing "ab" correctly with 100ms between the keys, exported to a file
+    fn export(format: Format) -> String {
+        let mut test = Test::new(vec!["ab".into()], true, false, true, None);
+        let start = Instant::now();
+        test.handle_key_at(KeyEvent::from(KeyCode::Char('a')), start);
+        test.handle_key_at(
+            KeyEvent::from(KeyCode::Char('b')),
+            start + Duration::from_millis(100),
+        );
+        assert!(test.complete);
+
+        let path = std::env::temp_dir().join(format!(
+            "ttyper-export-test-{}.{}",
+            std::process::id(),
+            format.extension()
+        ));
+        write_to_path(&Results::from(&test), format, &path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        text
+    }
+
+    #[test]
+    fn exports_json() {
+        let json: serde_json::Value = serde_json::from_str(&export(Format::Json)).unwrap();
+        assert_eq!(json["raw_wpm"], 120.0);
+        assert_eq!(json["overall_accuracy"], 1.0);
+        assert_eq!(json["accuracy"]["per_key"]["b"]["numerator"], 1);
+        assert_eq!(json["missed_words"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn exports_csv() {
+        let csv = export(Format::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "metric,key,value");
+        assert!(lines.contains(&"raw_wpm,,120"));
+        assert!(lines.contains(&"correct_keypresses,,2"));
+        assert!(lines.contains(&"key_accuracy,a,1"));
+    }
+
+    #[test]
+    fn escapes_csv_fields() {
+        assert_eq!(csv_field("a"), "a");
+        assert_eq!(csv_field(","), "\",\"");
+        assert_eq!(csv_field("\""), "\"\"\"\"");
+        assert_eq!(csv_field(" "), "\" \"");
+    }
+
+    #[test]
+    fn guesses_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("out.csv")), Format::Csv);
+        assert_eq!(Format::from_path(Path::new("out.CSV")), Format::Csv);
+        assert_eq!(Format::from_path(Path::new("out.json")), Format::Json);
+        assert_eq!(Format::from_path(Path::new("out")), Format::Json);
+    }
+}
@@ -0,0 +1,164 @@
+use rand::{seq::SliceRandom, Rng};
+
+// Bounds on the number of words in a generated sentence
+const MIN_SENTENCE_LENGTH: usize = 4;
+const MAX_SENTENCE_LENGTH: usize = 12;
+
+// Largest number inserted into tests
+const MAX_NUMBER: u32 = 9999;
+
+/// Punctuation conventions of a language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    /// Capitalize the first word of each sentence
+    pub capitalize: bool,
+    /// End sentences with periods, question or exclamation marks and separate clauses with commas
+    pub sentence_marks: bool,
+    /// Open questions and exclamations with inverted marks
+    pub inverted_marks: bool,
+}
+
+impl Rules {
+    pub fn for_language(name: &str) -> Self {
+        let mut rules = Self {
+            capitalize: true,
+            sentence_marks: true,
+            inverted_marks: false,
+        };
+
+        if name.starts_with("korean") {
+            // hangul has no letter case
+            rules.capitalize = false;
+        } else if name.starts_with("thai") {
+            // thai separates sentences with spaces rather than punctuation
+            rules.capitalize = false;
+            rules.sentence_marks = false;
+        } else if name.starts_with("spanish") || name.starts_with("galician") {
+            rules.inverted_marks = true;
+        }
+
+        rules
+    }
+}
+
+/// Split words into sentences, adding capitalization and punctuation according to the
+/// language's rules. `rate` is the chance of a word being followed by punctuation mid-sentence.
+pub fn punctuate<R: Rng + ?Sized>(words: &mut [String], rules: Rules, rate: f64, rng: &mut R) {
+    let mut start = 0;
+    while start < words.len() {
+        let length = rng
+            .gen_range(MIN_SENTENCE_LENGTH..=MAX_SENTENCE_LENGTH)
+            .min(words.len() - start);
+        let sentence = &mut words[start..start + length];
+        start += length;
+
+        let terminal = *['.', '.', '.', '.', '?', '!'].choose(rng).unwrap();
+        for (i, word) in sentence.iter_mut().enumerate() {
+            if i == 0 && rules.capitalize {
+                *word = capitalize(word);
+            }
+
+            if i == length - 1 {
+                if rules.sentence_marks {
+                    word.push(terminal);
+                }
+            } else if rng.gen_bool(rate) {
+                punctuate_word(word, rules, rng);
+            }
+
+            if i == 0 && rules.inverted_marks {
+                match terminal {
+                    '?' => word.insert(0, '¿'),
+                    '!' => word.insert(0, '¡'),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn punctuate_word<R: Rng + ?Sized>(word: &mut String, rules: Rules, rng: &mut R) {
+    let marks: &[&str] = if rules.sentence_marks {
+        &[",", ",", ",", ",", ";", ":", "\"", "(", "-"]
+    } else {
+        &["\"", "("]
+    };
+
+    match *marks.choose(rng).unwrap() {
+        "\"" => *word = format!("\"{}\"", word),
+        "(" => *word = format!("({})", word),
+        "-" => *word = format!("{} -", word),
+        mark => word.push_str(mark),
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Replace words with random numbers. `rate` is the chance of each word being replaced.
+pub fn insert_numbers<R: Rng + ?Sized>(words: &mut [String], rate: f64, rng: &mut R) {
+    for word in words.iter_mut() {
+        if rng.gen_bool(rate) {
+            *word = rng.gen_range(0..=MAX_NUMBER).to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn repeated(count: usize) -> Vec<String> {
+        vec![String::from("word"); count]
+    }
+
+    #[test]
+    fn punctuates_sentences() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut words = repeated(100);
+        punctuate(&mut words, Rules::for_language("english"), 0.5, &mut rng);
+
+        assert!(words[0].starts_with('W'));
+        assert!(words[99].ends_with(['.', '?', '!']));
+        for pair in words.windows(2) {
+            if pair[0].ends_with(['.', '?', '!']) {
+                assert!(pair[1].trim_start_matches(['"', '(']).starts_with('W'));
+            }
+        }
+    }
+
+    #[test]
+    fn follows_language_rules() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let mut words = repeated(100);
+        punctuate(&mut words, Rules::for_language("thai"), 0.5, &mut rng);
+        assert!(words.iter().all(|w| !w.contains(['.', ',', 'W'])));
+
+        let mut words = vec![String::from("qué"); 100];
+        punctuate(&mut words, Rules::for_language("spanish"), 0.0, &mut rng);
+        let count = |mark| words.iter().filter(|w| w.contains(mark)).count();
+        assert_eq!(count('¿'), count('?'));
+        assert_eq!(count('¡'), count('!'));
+        assert!(words[0].starts_with(['Q', '¿', '¡']));
+    }
+
+    #[test]
+    fn inserts_numbers() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let mut words = repeated(10);
+        insert_numbers(&mut words, 1.0, &mut rng);
+        assert!(words.iter().all(|w| w.parse::<u32>().is_ok()));
+
+        let mut words = repeated(10);
+        insert_numbers(&mut words, 0.0, &mut rng);
+        assert!(words.iter().all(|w| w == "word"));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::recording::Recording;
+use crate::test::{results::WPM_PER_CPS, Test, TestWord};
+
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// An opponent to race, shown as a second cursor moving through the prompt
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ghost {
+    /// Characters of the prompt passed at points in time since the start of the test, in order
+    samples: Vec<(Duration, usize)>,
+    /// Characters per second the ghost keeps moving at after its last sample
+    rate: f64,
+}
+
+impl Ghost {
+    /// A ghost typing at a constant speed, in words per minute
+    pub fn pace(wpm: f64) -> Self {
+        Self {
+            samples: vec![(Duration::ZERO, 0)],
+            rate: wpm / WPM_PER_CPS,
+        }
+    }
+
+    /// A ghost following a recorded run of the test. If the recording stops before the end of
+    /// the prompt, the ghost carries on at the recording's average speed.
+    pub fn from_recording(recording: &Recording) -> Self {
+        let mut test = recording.test();
+        let origin = Instant::now();
+        let mut samples = vec![(Duration::ZERO, 0)];
+        for key in &recording.keys {
+            let time = origin + Duration::from_secs_f64(key.time);
+            key.replay(&mut test, time);
+            match test.start {
+                Some(start) => samples.push((time - start, progress(&test))),
+                // sudden death restarted the test, so only the next attempt counts
+                None => samples.truncate(1),
+            }
+        }
+
+        let rate = match samples.last() {
+            Some(&(time, chars)) if !time.is_zero() => chars as f64 / time.as_secs_f64(),
+            _ => 0.0,
+        };
+        Self { samples, rate }
+    }
+
+    /// Characters of the prompt the ghost has passed after the given time
+    pub fn chars_at(&self, elapsed: Duration) -> usize {
+        let index = self.samples.partition_point(|&(time, _)| time <= elapsed);
+        match self.samples[..index].last() {
+            Some(&(time, chars)) if index == self.samples.len() => {
+                chars + ((elapsed - time).as_secs_f64() * self.rate) as usize
+            }
+            Some(&(_, chars)) => chars,
+            None => 0,
+        }
+    }
+
+    /// Time the ghost takes to pass the given number of characters, if it ever does
+    pub fn time_to_reach(&self, chars: usize) -> Option<Duration> {
+        if let Some(&(time, _)) = self.samples.iter().find(|&&(_, c)| c >= chars) {
+            return Some(time);
+        }
+
+        let &(time, reached) = self.samples.last()?;
+        let remaining = (chars - reached) as f64 / self.rate;
+        Duration::try_from_secs_f64(remaining)
+            .ok()
+            .map(|remaining| time + remaining)
+    }
+
+    /// Word the ghost is on during a test, and how many of its characters it has passed. The
+    /// ghost is on the space after a word once it has passed every character of it.
+    pub fn position(&self, test: &Test) -> (usize, usize) {
+        let mut chars = self.chars_at(test.elapsed().unwrap_or_default());
+        for (index, word) in test.words.iter().enumerate() {
+            let len = word.text.graphemes(true).count();
+            if chars <= len || index == test.words.len() - 1 {
+                return (index, chars.min(len));
+            }
+            chars -= len + 1;
+        }
+        (0, 0)
+    }
+
+    /// Seconds by which a finished test beat the ghost, negative if the ghost was faster
+    pub fn margin(&self, test: &Test) -> Option<f64> {
+        let start = test.start?;
+        let elapsed = match test.time_limit {
+            Some(_) => test.elapsed()?,
+            None => test
+                .words
+                .iter()
+                .flat_map(|word| &word.events)
+                .map(|event| event.time)
+                .max()?
+                .saturating_duration_since(start),
+        };
+
+        let ghost = self.time_to_reach(progress(test))?;
+        Some(ghost.as_secs_f64() - elapsed.as_secs_f64())
+    }
+}
+
+/// Characters of the prompt passed in a test, counting the space after each finished word
+pub fn progress(test: &Test) -> usize {
+    if test.complete {
+        return prompt_len(&test.words);
+    }
+
+    let word = &test.words[test.current_word];
+    prompt_len(&test.words[..test.current_word])
+        + usize::from(test.current_word > 0)
+        + word
+            .progress
+            .graphemes(true)
+            .count()
+            .min(word.text.graphemes(true).count())
+}
+
+/// Characters in a prompt of the given words, including the spaces between them. Characters are
+/// counted as graphemes, so an accented letter is one however it's encoded.
+pub fn prompt_len(words: &[TestWord]) -> usize {
+    words
+        .iter()
+        .map(|word| word.text.graphemes(true).count() + 1)
+        .sum::<usize>()
+        - usize::from(!words.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::Recorder;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn paces_through_the_prompt() {
+        // 60 WPM is 5 characters per second
+        let ghost = Ghost::pace(60.0);
+        assert_eq!(ghost.chars_at(Duration::from_secs(2)), 10);
+        assert_eq!(ghost.time_to_reach(15), Some(Duration::from_secs(3)));
+
+        let mut test = Test::new(vec!["abc".into(), "de".into()], true, false, true, None);
+        assert_eq!(ghost.position(&test), (0, 0));
+        test.start = Instant::now().checked_sub(Duration::from_millis(900));
+        assert_eq!(ghost.position(&test), (1, 0));
+        test.start = Instant::now().checked_sub(Duration::from_secs(5));
+        assert_eq!(ghost.position(&test), (1, 2));
+    }
+
+    #[test]
+    fn follows_a_recording() {
+        let mut test = Test::new(vec!["ab".into(), "c".into()], true, false, true, None);
+        let mut recorder = Recorder::default();
+        for c in "ab c".chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            recorder.record(key, None);
+            test.handle_key(key);
+        }
+        assert!(test.complete);
+        assert_eq!(progress(&test), 4);
+
+        let mut recording = recorder.finish(&test);
+        for (i, key) in recording.keys.iter_mut().enumerate() {
+            key.time = i as f64;
+        }
+        let ghost = Ghost::from_recording(&recording);
+        assert_eq!(ghost.chars_at(Duration::from_millis(1500)), 2);
+        assert_eq!(ghost.time_to_reach(3), Some(Duration::from_secs(2)));
+        assert_eq!(ghost.time_to_reach(4), Some(Duration::from_secs(3)));
+    }
+}
@@ -0,0 +1,354 @@
+use ttyper::test::{
+    results::{Fraction, Results},
+    ErrorPolicy,
+};
+
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, Write},
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Settings a test was taken with
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Mode {
+    pub time: Option<u64>,
+    #[serde(default)]
+    pub quote: bool,
+    #[serde(default)]
+    pub code: bool,
+    pub practice: bool,
+    pub backtracking: bool,
+    pub sudden_death: bool,
+    pub backspace: bool,
+    #[serde(default)]
+    pub errors: ErrorPolicy,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.time {
+            Some(secs) => write!(f, "{}s", secs)?,
+            None => write!(f, "words")?,
+        }
+        if self.quote {
+            write!(f, ", quote")?;
+        }
+        if self.code {
+            write!(f, ", code")?;
+        }
+        if self.practice {
+            write!(f, ", practice")?;
+        }
+        if !self.backtracking {
+            write!(f, ", no backtrack")?;
+        }
+        if self.sudden_death {
+            write!(f, ", sudden death")?;
+        }
+        if !self.backspace {
+            write!(f, ", no backspace")?;
+        }
+        if self.errors != ErrorPolicy::Normal {
+            write!(f, ", {}", self.errors)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single finished test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub language: String,
+    pub mode: Mode,
+    pub word_count: usize,
+    #[serde(deserialize_with = "finite")]
+    pub raw_wpm: f64,
+    #[serde(deserialize_with = "finite")]
+    pub adjusted_wpm: f64,
+    pub accuracy: Fraction,
+    pub per_key_accuracy: BTreeMap<char, Fraction>,
+    pub missed_words: Vec<String>,
+}
+
+impl Record {
+    pub fn new(results: &Results, language: String, mode: Mode, word_count: usize) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            language,
+            mode,
+            word_count,
+            // e.g. a test finished in an instant has no speed, which JSON can't hold as NaN
+            raw_wpm: finite_or_zero(results.raw_wpm()),
+            adjusted_wpm: finite_or_zero(results.adjusted_wpm()),
+            accuracy: results.accuracy.overall,
+            per_key_accuracy: results
+                .accuracy
+                .per_key
+                .iter()
+                .filter_map(|(key, acc)| match key.code {
+                    KeyCode::Char(c) => Some((c, *acc)),
+                    _ => None,
+                })
+                .fold(BTreeMap::new(), |mut map, (c, acc)| {
+                    // the same character may have been typed with different modifiers
+                    let entry = map.entry(c).or_insert(Fraction::new(0, 0));
+                    entry.numerator += acc.numerator;
+                    entry.denominator += acc.denominator;
+                    map
+                }),
+            missed_words: results.missed_words.clone(),
+        }
+    }
+
+    pub fn date(&self) -> Date {
+        Date::from_timestamp(self.timestamp)
+    }
+}
+
+/// The value if it's finite, or else zero
+fn finite_or_zero(value: f64) -> f64 {
+    if value.is_finite() {
+        value
+    } else {
+        0.0
+    }
+}
+
+/// Deserialize a number which may have been written as `null` because it wasn't finite
+fn finite<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(deserializer)?.map_or(0.0, finite_or_zero))
+}
+
+/// Append a record to the history file, creating it if necessary
+pub fn append(path: &Path, record: &Record) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
+
+/// Load all records from the history file, along with the number of lines which were skipped
+/// because they couldn't be parsed
+pub fn load(path: &Path) -> io::Result<(Vec<Record>, usize)> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(err) => return Err(err),
+    };
+    Ok(parse(io::BufReader::new(file)))
+}
+
+/// Parse records a line at a time, counting the lines which can't be parsed
+fn parse(reader: impl BufRead) -> (Vec<Record>, usize) {
+    let mut records = Vec::new();
+    let mut skipped = 0;
+    for line in reader.lines() {
+        if line.as_ref().is_ok_and(|line| line.trim().is_empty()) {
+            continue;
+        }
+        match line.ok().and_then(|line| serde_json::from_str(&line).ok()) {
+            Some(record) => records.push(record),
+            None => skipped += 1,
+        }
+    }
+    (records, skipped)
+}
+
+/// Print a summary of the given records, including personal bests per language and mode
+pub fn print_summary(records: &[Record]) {
+    if records.is_empty() {
+        println!("No tests found.");
+        return;
+    }
+
+    println!(
+        "{:<10}  {:<16}  {:<24}  {:>5}  {:>7}  {:>7}  {:>8}",
+        "date", "language", "mode", "words", "wpm", "raw", "accuracy"
+    );
+    for record in records {
+        println!(
+            "{:<10}  {:<16}  {:<24}  {:>5}  {:>7.1}  {:>7.1}  {:>7.1}%",
+            record.date(),
+            record.language,
+            record.mode.to_string(),
+            record.word_count,
+            record.adjusted_wpm,
+            record.raw_wpm,
+            f64::from(record.accuracy) * 100.0,
+        );
+    }
+
+    let count = records.len() as f64;
+    println!();
+    println!("tests: {}", records.len());
+    println!(
+        "average wpm: {:.1}",
+        records.iter().map(|r| r.adjusted_wpm).sum::<f64>() / count
+    );
+    println!(
+        "average accuracy: {:.1}%",
+        records
+            .iter()
+            .map(|r| f64::from(r.accuracy) * 100.0)
+            .sum::<f64>()
+            / count
+    );
+
+    let mut bests: BTreeMap<(&str, &Mode), &Record> = BTreeMap::new();
+    for record in records {
+        let best = bests
+            .entry((&record.language, &record.mode))
+            .or_insert(record);
+        if record.adjusted_wpm > best.adjusted_wpm {
+            *best = record;
+        }
+    }
+
+    println!();
+    println!("personal bests:");
+    for ((language, mode), record) in bests {
+        println!(
+            "- {} ({}): {:.1} wpm on {}",
+            language,
+            mode,
+            record.adjusted_wpm,
+            record.date()
+        );
+    }
+}
+
+/// A calendar date in UTC, stored as days since the Unix epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date(i64);
+
+impl Date {
+    pub fn from_timestamp(timestamp: u64) -> Self {
+        Self((timestamp / SECONDS_PER_DAY) as i64)
+    }
+
+    pub fn from_ymd(year: i64, month: u32, day: u32) -> Self {
+        // Howard Hinnant's days_from_civil algorithm
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = (month as i64 + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        Self(era * 146097 + doe - 719468)
+    }
+
+    pub fn ymd(self) -> (i64, u32, u32) {
+        // Howard Hinnant's civil_from_days algorithm
+        let z = self.0 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year, month, day)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid date `{}`, expected YYYY-MM-DD", s);
+
+        let mut parts = s.splitn(3, '-');
+        let mut next = || parts.next().ok_or_else(invalid);
+        let year: i64 = next()?.parse().map_err(|_| invalid())?;
+        let month: u32 = next()?.parse().map_err(|_| invalid())?;
+        let day: u32 = next()?.parse().map_err(|_| invalid())?;
+
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return Err(invalid());
+        }
+
+        Ok(Self::from_ymd(year, month, day))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_dates() {
+        assert_eq!(Date::from_ymd(1970, 1, 1), Date(0));
+        assert_eq!(Date::from_timestamp(951_782_400).ymd(), (2000, 2, 29));
+        assert_eq!(Date::from_ymd(2024, 12, 31).to_string(), "2024-12-31");
+
+        for days in -1000..1000 {
+            let (year, month, day) = Date(days).ymd();
+            assert_eq!(Date::from_ymd(year, month, day), Date(days));
+        }
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!("2021-03-04".parse(), Ok(Date::from_ymd(2021, 3, 4)));
+        assert!("2021-13-04".parse::<Date>().is_err());
+        assert!("yesterday".parse::<Date>().is_err());
+    }
+
+    #[test]
+    fn loads_tests_without_a_speed() {
+        let record = Record {
+            timestamp: 0,
+            language: String::from("english"),
+            mode: Mode {
+                time: None,
+                quote: false,
+                code: false,
+                practice: false,
+                backtracking: true,
+                sudden_death: false,
+                backspace: true,
+                errors: ErrorPolicy::Normal,
+            },
+            word_count: 1,
+            raw_wpm: finite_or_zero(f64::NAN),
+            adjusted_wpm: f64::NAN,
+            accuracy: Fraction::new(1, 1),
+            per_key_accuracy: BTreeMap::new(),
+            missed_words: Vec::new(),
+        };
+        // NaN is written as null, like records saved before it was mapped to zero
+        let line = serde_json::to_string(&record).unwrap();
+        assert!(line.contains("\"adjusted_wpm\":null"));
+
+        let text = format!("{}\nnot a record\n\n{}\n", line, line);
+        let (records, skipped) = parse(text.as_bytes());
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].raw_wpm, 0.0);
+        assert_eq!(records[0].adjusted_wpm, 0.0);
+        assert_eq!(skipped, 1);
+    }
+}
@@ -0,0 +1,464 @@
+use crate::test::Test;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+// Names of keys other than characters, as they're written in chords
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::Backspace, "backspace"),
+    (KeyCode::Enter, "enter"),
+    (KeyCode::Left, "left"),
+    (KeyCode::Right, "right"),
+    (KeyCode::Up, "up"),
+    (KeyCode::Down, "down"),
+    (KeyCode::Home, "home"),
+    (KeyCode::End, "end"),
+    (KeyCode::PageUp, "pageup"),
+    (KeyCode::PageDown, "pagedown"),
+    (KeyCode::Tab, "tab"),
+    (KeyCode::BackTab, "backtab"),
+    (KeyCode::Delete, "delete"),
+    (KeyCode::Insert, "insert"),
+    (KeyCode::Esc, "esc"),
+    (KeyCode::Char(' '), "space"),
+];
+
+// Names of modifiers, in the order they're written in chords
+const MODIFIER_NAMES: &[(KeyModifiers, &str)] = &[
+    (KeyModifiers::CONTROL, "ctrl"),
+    (KeyModifiers::ALT, "alt"),
+    (KeyModifiers::SHIFT, "shift"),
+];
+
+/// A key along with the modifiers held while pressing it, written like `ctrl-w`, `esc` or `q`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    /// The key pressed
+    pub code: KeyCode,
+    /// Modifiers held, other than shift for characters, which is part of the character
+    pub modifiers: KeyModifiers,
+}
+
+impl Chord {
+    /// A chord of a key and modifiers
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// Whether a key event is this chord being pressed
+    pub fn matches(&self, key: KeyEvent) -> bool {
+        let modifiers = match key.code {
+            // terminals disagree on whether shifted characters have the shift modifier
+            KeyCode::Char(_) | KeyCode::BackTab => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        key.code == self.code && modifiers == self.modifiers
+    }
+
+    /// Whether the chord types during a test on a screen, so it can't be bound there
+    pub fn types(&self, screen: Screen) -> bool {
+        let typing = match self.code {
+            KeyCode::Char(_) | KeyCode::Enter | KeyCode::Backspace => screen != Screen::Results,
+            KeyCode::Tab => screen == Screen::Code,
+            _ => false,
+        };
+        typing
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+impl FromStr for Chord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the key itself can be `-`, as in `ctrl--`
+        let (modifiers, key) = match s.strip_suffix('-') {
+            Some(rest) if rest.is_empty() || rest.ends_with('-') => (rest, "-"),
+            _ => s.rsplit_once('-').unwrap_or(("", s)),
+        };
+
+        let mut chord = Chord::new(KeyCode::Null, KeyModifiers::NONE);
+        for modifier in modifiers.split('-').filter(|m| !m.is_empty()) {
+            let (flag, _) = MODIFIER_NAMES
+                .iter()
+                .find(|(_, name)| modifier.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("unknown modifier `{}` in `{}`", modifier, s))?;
+            chord.modifiers |= *flag;
+        }
+
+        let mut chars = key.chars();
+        chord.code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match KEY_NAMES
+                .iter()
+                .find(|(_, name)| key.eq_ignore_ascii_case(name))
+            {
+                Some(&(code, _)) => code,
+                None => match key.strip_prefix(['f', 'F']).map(str::parse) {
+                    Some(Ok(n @ 1..=12)) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key `{}` in `{}`", key, s)),
+                },
+            },
+        };
+
+        // shifted keys are bound the way terminals report them
+        if chord.modifiers.contains(KeyModifiers::SHIFT) {
+            match chord.code {
+                KeyCode::Char(c) => chord.code = KeyCode::Char(c.to_ascii_uppercase()),
+                KeyCode::Tab => chord.code = KeyCode::BackTab,
+                _ => return Ok(chord),
+            }
+            chord.modifiers -= KeyModifiers::SHIFT;
+        }
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (flag, name) in MODIFIER_NAMES {
+            if self.modifiers.contains(*flag) {
+                write!(f, "{}-", name)?;
+            }
+        }
+        match (
+            self.code,
+            KEY_NAMES.iter().find(|(code, _)| *code == self.code),
+        ) {
+            (_, Some((_, name))) => write!(f, "{}", name),
+            (KeyCode::Char(c), None) => write!(f, "{}", c),
+            (KeyCode::F(n), None) => write!(f, "f{}", n),
+            (code, None) => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl Serialize for Chord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Chord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Screens on which keys are bound to actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    /// A test in progress, where keys which type, like characters and backspace, aren't bound
+    Test,
+    /// A source code test in progress, where tab types indentation as well
+    Code,
+    /// Results of a finished test
+    Results,
+}
+
+impl Screen {
+    /// Screen showing a test in progress
+    pub fn of(test: &Test) -> Self {
+        match test.code {
+            Some(_) => Screen::Code,
+            None => Screen::Test,
+        }
+    }
+}
+
+/// Something done with a key rather than by typing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    /// Finish a test early and show its results
+    AbortToResults,
+    /// Delete the word being typed, or go back to the last word if it's empty
+    DeleteWord,
+    /// Quit ttyper
+    Quit,
+    /// Start another test
+    Restart,
+    /// Start another test in the middle of one, skipping its results
+    QuickRestart,
+    /// Take the test again from the start on the same text
+    RestartSameText,
+    /// Practice the words missed in the test
+    Practice,
+    /// Practice the keys typed worst in the test
+    Drill,
+    /// Export the results to a file
+    Export,
+    /// Switch to the next results view
+    NextView,
+    /// Switch the statistic shown by the keyboard heatmap
+    NextMetric,
+}
+
+impl Action {
+    /// Every action, in the order they're looked up when keys are bound to more than one
+    pub const ALL: [Action; 11] = [
+        Action::AbortToResults,
+        Action::DeleteWord,
+        Action::Quit,
+        Action::Restart,
+        Action::QuickRestart,
+        Action::RestartSameText,
+        Action::Practice,
+        Action::Drill,
+        Action::Export,
+        Action::NextView,
+        Action::NextMetric,
+    ];
+
+    /// Whether the action can be taken on a screen
+    pub fn is_on(self, screen: Screen) -> bool {
+        let test = screen != Screen::Results;
+        match self {
+            Action::Quit | Action::RestartSameText => true,
+            Action::AbortToResults | Action::DeleteWord | Action::QuickRestart => test,
+            _ => !test,
+        }
+    }
+}
+
+/// Keys bound to each action, from the `[keys]` table of the config. Each action can be bound to
+/// a single chord or a list of them, and an empty list unbinds it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct KeyBindings {
+    /// Quit ttyper from any screen
+    #[serde(deserialize_with = "one_or_many")]
+    pub quit: Vec<Chord>,
+    /// Finish a test early and show its results
+    #[serde(deserialize_with = "one_or_many")]
+    pub abort_to_results: Vec<Chord>,
+    /// Delete the word being typed, or go back to the last word if it's empty
+    #[serde(deserialize_with = "one_or_many")]
+    pub delete_word: Vec<Chord>,
+    /// Start another test from the results
+    #[serde(deserialize_with = "one_or_many")]
+    pub restart: Vec<Chord>,
+    /// Start another test in the middle of one, skipping its results
+    #[serde(deserialize_with = "one_or_many")]
+    pub quick_restart: Vec<Chord>,
+    /// Take the test again from the start on the same text, from any screen
+    #[serde(deserialize_with = "one_or_many")]
+    pub restart_same_text: Vec<Chord>,
+    /// Practice the words missed in the test
+    #[serde(deserialize_with = "one_or_many")]
+    pub practice: Vec<Chord>,
+    /// Practice the keys typed worst in the test
+    #[serde(deserialize_with = "one_or_many")]
+    pub drill: Vec<Chord>,
+    /// Export the results to a file
+    #[serde(deserialize_with = "one_or_many")]
+    pub export: Vec<Chord>,
+    /// Switch to the next results view
+    #[serde(deserialize_with = "one_or_many")]
+    pub next_view: Vec<Chord>,
+    /// Switch the statistic shown by the keyboard heatmap
+    #[serde(deserialize_with = "one_or_many")]
+    pub next_metric: Vec<Chord>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let key = |c| Chord::new(KeyCode::Char(c), KeyModifiers::NONE);
+        let ctrl = |c| Chord::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        Self {
+            quit: vec![
+                key('q'),
+                Chord::new(KeyCode::Esc, KeyModifiers::NONE),
+                ctrl('c'),
+            ],
+            abort_to_results: vec![Chord::new(KeyCode::Esc, KeyModifiers::NONE)],
+            // terminals send CTRL-H for CTRL-Backspace
+            delete_word: vec![ctrl('w'), ctrl('h')],
+            restart: vec![key('r')],
+            // tab types indentation in code tests, where it doesn't restart
+            quick_restart: vec![Chord::new(KeyCode::Tab, KeyModifiers::NONE)],
+            restart_same_text: vec![key('s'), Chord::new(KeyCode::BackTab, KeyModifiers::NONE)],
+            practice: vec![key('p')],
+            drill: vec![key('w')],
+            export: vec![key('e')],
+            next_view: vec![Chord::new(KeyCode::Tab, KeyModifiers::NONE)],
+            next_metric: vec![key('m')],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Chords bound to an action
+    pub fn chords(&self, action: Action) -> &[Chord] {
+        match action {
+            Action::Quit => &self.quit,
+            Action::AbortToResults => &self.abort_to_results,
+            Action::DeleteWord => &self.delete_word,
+            Action::Restart => &self.restart,
+            Action::QuickRestart => &self.quick_restart,
+            Action::RestartSameText => &self.restart_same_text,
+            Action::Practice => &self.practice,
+            Action::Drill => &self.drill,
+            Action::Export => &self.export,
+            Action::NextView => &self.next_view,
+            Action::NextMetric => &self.next_metric,
+        }
+    }
+
+    /// Action a key event is bound to on a screen, if any
+    pub fn action(&self, screen: Screen, key: KeyEvent) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .filter(|action| action.is_on(screen))
+            .find(|&action| {
+                self.chords(action)
+                    .iter()
+                    .any(|chord| chord.matches(key) && !chord.types(screen))
+            })
+    }
+
+    /// Name of the first key bound to an action, quoted if it's a character, for prompts
+    pub fn name(&self, action: Action) -> Option<String> {
+        self.chords(action).first().map(|chord| match chord.code {
+            KeyCode::Char(c) if chord.modifiers.is_empty() && c != ' ' => format!("'{}'", c),
+            _ => chord.to_string(),
+        })
+    }
+
+    /// Sentence telling which keys to press for the given actions and what they do, like
+    /// `Press 'q' to quit or 'r' for another test`. Unbound actions are left out.
+    pub fn prompt(&self, actions: &[(Action, &str)]) -> String {
+        let parts: Vec<String> = actions
+            .iter()
+            .filter_map(|(action, does)| Some(format!("{} {}", self.name(*action)?, does)))
+            .collect();
+        match parts.split_last() {
+            Some((last, [])) => format!("Press {}", last),
+            Some((last, rest)) => format!("Press {} or {}", rest.join(", "), last),
+            None => String::new(),
+        }
+    }
+}
+
+/// Deserialize a list of chords, which can also be a single chord
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Chord>, D::Error> {
+    struct ChordsVisitor;
+
+    impl<'de> de::Visitor<'de> for ChordsVisitor {
+        type Value = Vec<Chord>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a key like \"ctrl-w\" or a list of them")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            value.parse().map(|chord| vec![chord]).map_err(E::custom)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            Vec::deserialize(de::value::SeqAccessDeserializer::new(seq))
+        }
+    }
+
+    deserializer.deserialize_any(ChordsVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chords() {
+        for (text, code, modifiers) in [
+            ("q", KeyCode::Char('q'), KeyModifiers::NONE),
+            ("ctrl-w", KeyCode::Char('w'), KeyModifiers::CONTROL),
+            (
+                "Ctrl-Alt-Backspace",
+                KeyCode::Backspace,
+                KeyModifiers::CONTROL | KeyModifiers::ALT,
+            ),
+            ("shift-r", KeyCode::Char('R'), KeyModifiers::NONE),
+            ("ctrl--", KeyCode::Char('-'), KeyModifiers::CONTROL),
+            ("-", KeyCode::Char('-'), KeyModifiers::NONE),
+            ("f5", KeyCode::F(5), KeyModifiers::NONE),
+            ("space", KeyCode::Char(' '), KeyModifiers::NONE),
+            ("shift-tab", KeyCode::BackTab, KeyModifiers::NONE),
+            ("backtab", KeyCode::BackTab, KeyModifiers::NONE),
+        ] {
+            let chord: Chord = text.parse().unwrap();
+            assert_eq!(chord, Chord::new(code, modifiers), "{}", text);
+            assert_eq!(chord.to_string().parse::<Chord>(), Ok(chord));
+        }
+        assert!("hyper-q".parse::<Chord>().is_err());
+        assert!("ctrl-escape".parse::<Chord>().is_err());
+        assert!("f13".parse::<Chord>().is_err());
+    }
+
+    #[test]
+    fn dispatches_actions_by_screen() {
+        let keys = KeyBindings::default();
+        let esc = KeyEvent::from(KeyCode::Esc);
+        assert_eq!(keys.action(Screen::Test, esc), Some(Action::AbortToResults));
+        assert_eq!(keys.action(Screen::Results, esc), Some(Action::Quit));
+
+        // keys which type can't be bound during a test
+        let q = KeyEvent::from(KeyCode::Char('q'));
+        assert_eq!(keys.action(Screen::Test, q), None);
+        assert_eq!(keys.action(Screen::Results, q), Some(Action::Quit));
+
+        let ctrl_w = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(keys.action(Screen::Test, ctrl_w), Some(Action::DeleteWord));
+        assert_eq!(keys.action(Screen::Results, ctrl_w), None);
+
+        // tab types indentation in code
+        let tab = KeyEvent::from(KeyCode::Tab);
+        assert_eq!(keys.action(Screen::Test, tab), Some(Action::QuickRestart));
+        assert_eq!(keys.action(Screen::Code, tab), None);
+        assert_eq!(keys.action(Screen::Results, tab), Some(Action::NextView));
+        let backtab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(
+            keys.action(Screen::Code, backtab),
+            Some(Action::RestartSameText)
+        );
+        let s = KeyEvent::from(KeyCode::Char('s'));
+        assert_eq!(
+            keys.action(Screen::Results, s),
+            Some(Action::RestartSameText)
+        );
+
+        let keys: KeyBindings =
+            toml::from_str("restart = \"shift-n\"\nquit = []\ndelete-word = [\"alt-backspace\"]")
+                .unwrap();
+        let shift_n = KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT);
+        assert_eq!(keys.action(Screen::Results, shift_n), Some(Action::Restart));
+        assert_eq!(keys.action(Screen::Results, q), None);
+        assert_eq!(keys.action(Screen::Test, ctrl_w), None);
+        assert_eq!(keys.export, KeyBindings::default().export);
+    }
+
+    #[test]
+    fn prompts_with_bound_keys() {
+        let mut keys = KeyBindings::default();
+        let actions = [
+            (Action::Quit, "to quit"),
+            (Action::Restart, "for another test"),
+            (Action::NextView, "to switch views"),
+        ];
+        assert_eq!(
+            keys.prompt(&actions),
+            "Press 'q' to quit, 'r' for another test or tab to switch views"
+        );
+
+        keys.quit = vec!["ctrl-d".parse().unwrap()];
+        keys.next_view.clear();
+        assert_eq!(
+            keys.prompt(&actions),
+            "Press ctrl-d to quit or 'r' for another test"
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use crate::error::{self, Error};
+use crate::read_text;
+
+use serde::de::DeserializeOwned;
+use std::{
+    env, fmt,
+    path::{Path, PathBuf},
+};
+use ttyper::config::Theme;
+
+/// Name of the config file read from the current directory or its nearest ancestor
+pub const PROJECT_CONFIG: &str = ".ttyper.toml";
+
+/// Config file layers, from the lowest priority to the highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    /// Shared by every user of the machine
+    System,
+    /// In the user's config directory, or given with --config
+    User,
+    /// Found in the current directory or one of its ancestors
+    Project,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Layer::System => "system",
+            Layer::User => "user",
+            Layer::Project => "project",
+        })
+    }
+}
+
+/// Where the effective value of a setting came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Nothing set it, so it has its built-in default
+    Default,
+    /// A config file
+    File(Layer, PathBuf),
+    /// The profile selected with --profile
+    Profile(String),
+    /// The preset or installed theme which the config's theme is based on
+    Theme(String),
+    /// An environment variable
+    Env(String),
+    /// A command line argument
+    CommandLine,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(layer, path) => write!(f, "{} config {}", layer, path.display()),
+            Source::Profile(name) => write!(f, "profile {}", name),
+            Source::Theme(name) => write!(f, "theme {}", name),
+            Source::Env(var) => write!(f, "environment variable {}", var),
+            Source::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// A config file which was found and parsed
+#[derive(Debug)]
+struct File {
+    layer: Layer,
+    path: PathBuf,
+    text: String,
+    table: toml::Table,
+}
+
+/// Config files of every layer, which are merged so that higher layers override lower ones
+#[derive(Debug, Default)]
+pub struct Layers {
+    // ordered from the lowest priority to the highest
+    files: Vec<File>,
+    /// Name and settings of the selected profile, which override every file
+    profile: Option<(String, toml::Table)>,
+    /// Name and styles of the theme the `theme` table is based on, which every file overrides
+    theme: Option<(String, toml::Table)>,
+}
+
+impl Layers {
+    /// Read the system, user and project config files, skipping those which don't exist
+    pub fn load(user: Option<PathBuf>) -> error::Result<Self> {
+        let mut layers = Self::default();
+        for (layer, path) in paths(user) {
+            if let Some(text) = read_text(&path)? {
+                layers.push(layer, path, text)?;
+            }
+        }
+        Ok(layers)
+    }
+
+    /// Add a config file above the existing ones
+    pub fn push(&mut self, layer: Layer, path: PathBuf, text: String) -> error::Result<()> {
+        let mut table: toml::Table = text
+            .parse()
+            .map_err(|err| Error::config(path.clone(), &text, err))?;
+
+        // a theme given by name can have its styles overridden by higher layers
+        name_theme(&mut table);
+        if let Some(toml::Value::Table(profiles)) = table.get_mut("profile") {
+            for (_, profile) in profiles.iter_mut() {
+                if let Some(profile) = profile.as_table_mut() {
+                    name_theme(profile);
+                }
+            }
+        }
+
+        self.files.push(File {
+            layer,
+            path,
+            text,
+            table,
+        });
+        Ok(())
+    }
+
+    /// Select a profile defined in the `profile` table of any layer, whose settings override
+    /// those of every layer
+    pub fn select_profile(&mut self, name: &str) -> error::Result<()> {
+        self.profile = None;
+        let merged = self.merged();
+        let profiles = merged.get("profile").and_then(toml::Value::as_table);
+
+        match profiles.and_then(|profiles| profiles.get(name)?.as_table()) {
+            Some(profile) => {
+                self.profile = Some((name.to_owned(), profile.clone()));
+                Ok(())
+            }
+            None => Err(Error::UnknownProfile {
+                name: name.to_owned(),
+                available: profiles
+                    .map(|profiles| profiles.keys().cloned().collect())
+                    .unwrap_or_default(),
+            }),
+        }
+    }
+
+    /// Name of the theme the `theme` table is based on, if it's based on one
+    pub fn theme_name(&self) -> Option<String> {
+        let merged = self.merged();
+        let name = lookup(&merged, &["theme", "name"])?.as_str()?;
+        Some(name.to_owned())
+    }
+
+    /// Base the `theme` table on a preset or installed theme, which has the given styles
+    pub fn set_theme(&mut self, name: &str, path: PathBuf, text: &str) -> error::Result<()> {
+        let theme: toml::Table = text
+            .parse()
+            .map_err(|err| Error::config(path.clone(), text, err))?;
+        toml::from_str::<Theme>(text).map_err(|err| Error::config(path, text, err))?;
+
+        self.theme = Some((name.to_owned(), theme));
+        Ok(())
+    }
+
+    /// Settings of every layer merged together, where tables are merged key by key
+    pub fn merged(&self) -> toml::Table {
+        let mut merged = toml::Table::new();
+        if let Some((_, theme)) = &self.theme {
+            merged.insert("theme".into(), theme.clone().into());
+        }
+        for file in &self.files {
+            merge(&mut merged, file.table.clone());
+        }
+        if let Some((_, profile)) = &self.profile {
+            merge(&mut merged, profile.clone());
+        }
+        if let Some((name, _)) = &self.theme {
+            let mut theme = toml::Table::new();
+            theme.insert("name".into(), name.clone().into());
+            merge(
+                &mut merged,
+                toml::Table::from_iter([("theme".into(), theme.into())]),
+            );
+        }
+        merged
+    }
+
+    /// Deserialize the merged settings
+    pub fn get<T: DeserializeOwned>(&self) -> error::Result<T> {
+        // check each file on its own first, so errors point into the file which caused them
+        for file in &self.files {
+            toml::from_str::<T>(&file.text)
+                .map_err(|err| Error::config(file.path.clone(), &file.text, err))?;
+        }
+
+        // so an error in the merged settings can only come from the profile
+        toml::Value::Table(self.merged())
+            .try_into()
+            .map_err(|err: toml::de::Error| {
+                let name = self.profile.as_ref().map_or("", |(name, _)| name);
+                let file = self
+                    .files
+                    .iter()
+                    .rev()
+                    .find(|file| lookup(&file.table, &["profile", name]).is_some())
+                    .or(self.files.last());
+                Error::Config {
+                    path: file.map_or_else(PathBuf::new, |file| file.path.clone()),
+                    position: None,
+                    message: format!("in profile `{}`: {}", name, err.message()),
+                }
+            })
+    }
+
+    /// Profile or config file which sets the setting at the given path of keys, if any does
+    pub fn source(&self, keys: &[&str]) -> Source {
+        if let Some((name, profile)) = &self.profile {
+            if lookup(profile, keys).is_some() {
+                return Source::Profile(name.clone());
+            }
+        }
+
+        let file = self
+            .files
+            .iter()
+            .rev()
+            .find(|file| lookup(&file.table, keys).is_some());
+        match (file, &self.theme) {
+            (Some(file), _) => Source::File(file.layer, file.path.clone()),
+            (None, Some((name, theme)))
+                if keys.first() == Some(&"theme") && lookup(theme, &keys[1..]).is_some() =>
+            {
+                Source::Theme(name.clone())
+            }
+            (None, _) => Source::Default,
+        }
+    }
+}
+
+/// Paths the config file of each layer would be at, where there can only be a project config
+/// if one exists
+pub fn paths(user: Option<PathBuf>) -> Vec<(Layer, PathBuf)> {
+    let project = env::current_dir().ok().and_then(|dir| {
+        dir.ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG))
+            .find(|path| path.is_file())
+    });
+
+    [
+        (Layer::System, system_config()),
+        (Layer::User, user),
+        (Layer::Project, project),
+    ]
+    .into_iter()
+    .filter_map(|(layer, path)| Some((layer, path?)))
+    .collect()
+}
+
+/// Path of the config file shared by every user of the machine
+fn system_config() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env::var_os("PROGRAMDATA").map(|dir| Path::new(&dir).join("ttyper").join("config.toml"))
+    } else {
+        Some(PathBuf::from("/etc/ttyper/config.toml"))
+    }
+}
+
+/// Replace a theme given by name with a table naming it, so it can be merged with other tables
+fn name_theme(table: &mut toml::Table) {
+    if let Some(toml::Value::String(name)) = table.get("theme") {
+        let theme = toml::Table::from_iter([("name".to_owned(), name.clone().into())]);
+        table.insert("theme".into(), theme.into());
+    }
+}
+
+/// Merge a table into another, overriding its values except for tables, which are merged
+fn merge(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overrides)) => {
+                merge(base, overrides)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Value at a path of keys through nested tables
+fn lookup<'a>(table: &'a toml::Table, keys: &[&str]) -> Option<&'a toml::Value> {
+    let (last, tables) = keys.split_last()?;
+    let mut table = table;
+    for key in tables {
+        table = table.get(*key)?.as_table()?;
+    }
+    table.get(*last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ttyper::config::Config;
+
+    fn layers(files: &[(Layer, &str)]) -> Layers {
+        let mut layers = Layers::default();
+        for (layer, text) in files {
+            let path = PathBuf::from(format!("{}.toml", layer));
+            layers.push(*layer, path, text.to_string()).unwrap();
+        }
+        layers
+    }
+
+    #[test]
+    fn higher_layers_override_lower_ones() {
+        let layers = layers(&[
+            (
+                Layer::System,
+                "show_hud = false\n[theme]\ntitle = \"red\"\nborder_type = \"plain\"\n",
+            ),
+            (Layer::User, "default_language = \"german\"\n"),
+            (
+                Layer::Project,
+                "show_hud = true\n[theme]\ntitle = \"blue\"\n",
+            ),
+        ]);
+
+        let config: Config = layers.get().unwrap();
+        assert_eq!(config.default_language, "german");
+        assert!(config.show_hud);
+        assert_eq!(
+            config.theme.title,
+            ratatui::style::Style::default().fg(ratatui::style::Color::Blue)
+        );
+        assert_eq!(
+            config.theme.border_type,
+            ratatui::widgets::BorderType::Plain
+        );
+
+        let file = |layer| Source::File(layer, PathBuf::from(format!("{}.toml", layer)));
+        assert_eq!(layers.source(&["show_hud"]), file(Layer::Project));
+        assert_eq!(
+            layers.source(&["theme", "border_type"]),
+            file(Layer::System)
+        );
+        assert_eq!(layers.source(&["default_language"]), file(Layer::User));
+        assert_eq!(layers.source(&["show_keyboard"]), Source::Default);
+    }
+
+    #[test]
+    fn locates_errors_in_their_layer() {
+        let layers = layers(&[
+            (Layer::User, "show_hud = false\n"),
+            (Layer::Project, "\nshow_hud = 3\n"),
+        ]);
+        match layers.get::<Config>() {
+            Err(Error::Config { path, position, .. }) => {
+                assert_eq!(path, PathBuf::from("project.toml"));
+                assert_eq!(position, Some((2, 12)));
+            }
+            result => panic!("expected a config error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn themes_are_overridden_by_every_layer() {
+        let mut layers = layers(&[
+            (Layer::User, "theme = \"nord\"\n"),
+            (Layer::Project, "[theme]\ntitle = \"red\"\n"),
+        ]);
+        assert_eq!(layers.theme_name().as_deref(), Some("nord"));
+        layers
+            .set_theme(
+                "nord",
+                PathBuf::from("nord.toml"),
+                "title = \"blue\"\nprompt_border = \"green\"\n",
+            )
+            .unwrap();
+
+        let config: Config = layers.get().unwrap();
+        assert_eq!(config.theme.name.as_deref(), Some("nord"));
+        assert_eq!(
+            config.theme.title,
+            ratatui::style::Style::default().fg(ratatui::style::Color::Red)
+        );
+        assert_eq!(
+            layers.source(&["theme", "prompt_border"]),
+            Source::Theme("nord".into())
+        );
+        assert_eq!(layers.source(&["theme", "hud_label"]), Source::Default);
+    }
+
+    #[test]
+    fn profiles_override_every_layer() {
+        let mut layers = layers(&[
+            (
+                Layer::User,
+                "default_language = \"german\"\n[profile.exam]\ndefault_language = \"english1000\"\nshow_hud = false\n",
+            ),
+            (Layer::Project, "show_hud = true\n[profile.exam.theme]\ntitle = \"red\"\n"),
+        ]);
+        layers.select_profile("exam").unwrap();
+
+        let config: Config = layers.get().unwrap();
+        assert_eq!(config.default_language, "english1000");
+        assert!(!config.show_hud);
+        assert_eq!(
+            config.theme.title,
+            ratatui::style::Style::default().fg(ratatui::style::Color::Red)
+        );
+        assert_eq!(config.profile.len(), 1);
+        assert_eq!(layers.source(&["show_hud"]), Source::Profile("exam".into()));
+
+        match layers.select_profile("warmup") {
+            Err(Error::UnknownProfile { available, .. }) => assert_eq!(available, ["exam"]),
+            result => panic!("expected an unknown profile error, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use crate::config::KeyboardLayout;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Shifted characters of each row of a US QWERTY keyboard, from the number row down
+const QWERTY_SHIFTED: [&str; 4] = [
+    "~!@#$%^&*()_+",
+    "QWERTYUIOP{}|",
+    "ASDFGHJKL:\"",
+    "ZXCVBNM<>?",
+];
+
+/// A keyboard layout emulated on a keyboard set up as US QWERTY. Each typed character is
+/// replaced with the one on the same key in this layout, so it can be learned without changing
+/// the layout of the operating system.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "LayoutFile")]
+pub struct Layout {
+    /// Unshifted characters of each row, from the number row down
+    rows: [String; 4],
+    /// Characters typed on QWERTY, mapped to the ones on the same keys in this layout
+    remap: HashMap<char, char>,
+}
+
+/// Contents of a layout file, which gives the characters of every key of the layout
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayoutFile {
+    /// Unshifted characters of each row, from the number row down
+    rows: [String; 4],
+    /// Shifted characters of each row, from the number row down
+    shifted: [String; 4],
+}
+
+impl TryFrom<LayoutFile> for Layout {
+    type Error = String;
+
+    fn try_from(file: LayoutFile) -> Result<Self, Self::Error> {
+        let mut remap = HashMap::new();
+        for (layer, qwerty) in [
+            (&file.rows, KeyboardLayout::Qwerty.rows()),
+            (&file.shifted, QWERTY_SHIFTED),
+        ] {
+            for (row, qwerty) in layer.iter().zip(qwerty) {
+                if row.chars().count() != qwerty.chars().count() {
+                    return Err(format!(
+                        "row `{}` has {} keys, but QWERTY's has {}",
+                        row,
+                        row.chars().count(),
+                        qwerty.chars().count()
+                    ));
+                }
+                remap.extend(qwerty.chars().zip(row.chars()));
+            }
+        }
+
+        Ok(Self {
+            rows: file.rows,
+            remap,
+        })
+    }
+}
+
+impl Layout {
+    /// Unshifted characters of each row, from the number row down
+    pub fn rows(&self) -> [&str; 4] {
+        [0, 1, 2, 3].map(|i| self.rows[i].as_str())
+    }
+
+    /// Character typed in this layout by the key which types the given character on QWERTY
+    pub fn remap_char(&self, c: char) -> char {
+        self.remap.get(&c).copied().unwrap_or(c)
+    }
+
+    /// Key event for the key pressed in this layout. Keys other than characters are left alone,
+    /// as are shortcuts like CTRL-W, which follow the layout of the operating system.
+    pub fn remap(&self, key: KeyEvent) -> KeyEvent {
+        let shortcut = key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Char(c) if !shortcut => KeyEvent {
+                code: KeyCode::Char(self.remap_char(c)),
+                ..key
+            },
+            _ => key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLEMAK: &str = r#"
+rows = ["`1234567890-=", "qwfpgjluy;[]\\", "arstdhneio'", "zxcvbkm,./"]
+shifted = ["~!@#$%^&*()_+", "QWFPGJLUY:{}|", "ARSTDHNEIO\"", "ZXCVBKM<>?"]
+"#;
+
+    #[test]
+    fn remaps_qwerty_keys() {
+        let layout: Layout = toml::from_str(COLEMAK).unwrap();
+        let typed: String = "asdf jkl; ASDF"
+            .chars()
+            .map(|c| layout.remap_char(c))
+            .collect();
+        assert_eq!(typed, "arst neio ARST");
+        assert_eq!(layout.rows(), KeyboardLayout::Colemak.rows());
+
+        let key = KeyEvent::from(KeyCode::Char('e'));
+        assert_eq!(layout.remap(key).code, KeyCode::Char('f'));
+        assert_eq!(
+            layout.remap(KeyEvent::from(KeyCode::Enter)).code,
+            KeyCode::Enter
+        );
+        let key = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(layout.remap(key), key);
+    }
+
+    #[test]
+    fn rejects_rows_of_the_wrong_length() {
+        let short = COLEMAK.replace("arstdhneio'", "arstdhneio");
+        assert!(toml::from_str::<Layout>(&short)
+            .unwrap_err()
+            .message()
+            .contains("has 10 keys"));
+    }
+}
@@ -0,0 +1,36 @@
+//! The typing test engine and terminal widgets behind the `ttyper` binary.
+//!
+//! A [`Test`](test::Test) is created from a list of words and fed crossterm key events with
+//! [`Test::handle_key`](test::Test::handle_key). Once it is complete, its
+//! [`Results`](test::results::Results) can be computed with `Results::from(&test)`.
+//!
+//! Both can be drawn with ratatui by applying a [`Theme`](config::Theme) to them or to one of the
+//! widgets in [`ui`]:
+//!
+//! ```
+//! use ttyper::{config::Theme, test::Test};
+//!
+//! let theme = Theme::default();
+//! let test = Test::new(vec![String::from("hello")], true, false, true, None);
+//! let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 24))?;
+//! terminal.draw(|f| f.render_widget(theme.apply_to(&test), f.size()))?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+#![warn(missing_docs)]
+
+/// Configuration and the styles widgets are drawn with
+pub mod config;
+/// Opponents to race against during a test
+pub mod ghost;
+/// Keys bound to the actions of the test and results screens
+pub mod keys;
+/// Emulating keyboard layouts on a keyboard set up as QWERTY
+pub mod layout;
+/// Messages and standings of races between players over a network
+pub mod race;
+/// Recording tests and playing them back
+pub mod recording;
+/// Typing tests and their results
+pub mod test;
+/// Widgets drawing tests and results with a [`Theme`](config::Theme)
+pub mod ui;
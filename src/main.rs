@@ -1,39 +1,75 @@
-mod config;
-mod test;
-mod ui;
+mod check;
+mod error;
+mod export;
+mod generate;
+mod history;
+mod layers;
+mod multiplayer;
+mod options;
+mod practice;
+mod quote;
+mod snippet;
 
-use config::Config;
-use test::{results::Results, Test};
+use error::Error;
 
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{
+    builder::BoolishValueParser, Arg, ArgAction, ArgMatches, CommandFactory, FromArgMatches,
+    Parser, Subcommand,
+};
 use clap_complete::{generate, Shell};
 use crossterm::{
     self, cursor,
-    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    event::{self, Event, KeyEventKind},
     execute, terminal,
 };
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    terminal::Terminal,
+    layout::{Constraint, Direction, Layout, Rect},
+    terminal::{Frame, Terminal},
 };
 use rust_embed::RustEmbed;
 use std::{
     ffi::OsString,
     fs,
     io::{self, BufRead},
-    num,
-    path::PathBuf,
+    num, panic,
+    path::{Path, PathBuf},
+    process::ExitCode,
     str,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use ttyper::{
+    config::Config,
+    ghost::Ghost,
+    keys::{Action, Screen},
+    layout,
+    recording::{Recorder, Recording},
+    test::{results::Results, CodeOptions, ErrorPolicy, Test},
+    ui,
 };
 
+// Number of words generated at a time for timed tests
+const TIMED_BATCH: usize = 50;
+
+// Interval at which the HUD is redrawn while no keys are pressed
+const HUD_TICK: Duration = Duration::from_millis(200);
+
+// Interval at which the ghost's cursor is moved, which is short enough for it to move smoothly
+const GHOST_TICK: Duration = Duration::from_millis(50);
+
+// Streams of the seeded RNG used to derive the seed of the test after a restart or practice
+const RESTART_STREAM: u64 = 1;
+const PRACTICE_STREAM: u64 = 2;
+const DRILL_STREAM: u64 = 3;
+
 #[derive(RustEmbed)]
 #[folder = "resources/runtime"]
 struct Resources;
 
 #[derive(Debug, Parser)]
-#[command(about, version)]
+#[command(about, version, mut_args(bool_flag))]
 struct Opt {
     /// Read test contents from the specified file, or "-" for stdin
     #[arg(value_name = "PATH")]
@@ -43,41 +79,197 @@ struct Opt {
     debug: bool,
 
     /// Specify word count
-    #[arg(short, long, value_name = "N", default_value = "50")]
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        default_value = "50",
+        env = "TTYPER_WORDS"
+    )]
     words: num::NonZeroUsize,
 
+    /// Specify time limit in seconds, generating words as needed
+    #[arg(short, long, value_name = "SECONDS", env = "TTYPER_TIME")]
+    time: Option<num::NonZeroU64>,
+
     /// Use config file
-    #[arg(short, long, value_name = "PATH")]
+    #[arg(short, long, value_name = "PATH", env = "TTYPER_CONFIG")]
     config: Option<PathBuf>,
 
     /// Specify test language in file
-    #[arg(long, value_name = "PATH")]
+    #[arg(long, value_name = "PATH", env = "TTYPER_LANGUAGE_FILE")]
     language_file: Option<PathBuf>,
 
     /// Specify test language
-    #[arg(short, long, value_name = "LANG")]
+    #[arg(short, long, value_name = "LANG", env = "TTYPER_LANGUAGE")]
     language: Option<String>,
 
     /// List installed languages
     #[arg(long)]
     list_languages: bool,
 
-    /// Disable backtracking to completed words
+    /// Use a profile from the config, overriding its other settings
+    #[arg(short, long, value_name = "NAME", env = "TTYPER_PROFILE")]
+    profile: Option<String>,
+
+    /// List profiles in the config
+    #[arg(long)]
+    list_profiles: bool,
+
+    /// Use a preset or installed theme, which the config's theme overrides
+    #[arg(long, value_name = "NAME", env = "TTYPER_THEME")]
+    theme: Option<String>,
+
+    /// List preset and installed themes
+    #[arg(long)]
+    list_themes: bool,
+
+    /// Emulate a keyboard layout, remapping keys typed on QWERTY to the same keys in the layout
+    #[arg(long, value_name = "NAME", env = "TTYPER_LAYOUT")]
+    layout: Option<String>,
+
+    /// List preset and installed keyboard layouts
     #[arg(long)]
+    list_layouts: bool,
+
+    /// Layout loaded from the file named by --layout
+    #[arg(skip)]
+    emulated: Option<layout::Layout>,
+
+    /// Type a quote instead of random words, optionally of the given length or ID
+    #[arg(
+        short,
+        long,
+        value_name = "LENGTH|ID",
+        num_args = 0..=1,
+        default_missing_value = "any",
+        env = "TTYPER_QUOTE"
+    )]
+    quote: Option<quote::Selector>,
+
+    /// Type source code, keeping its lines and indentation
+    #[arg(long, env = "TTYPER_CODE")]
+    code: bool,
+
+    /// Specify width of tabs in code
+    #[arg(long, value_name = "N", default_value = "4", env = "TTYPER_TAB_WIDTH")]
+    tab_width: num::NonZeroUsize,
+
+    /// Require typing the indentation of code
+    #[arg(long, env = "TTYPER_REQUIRE_INDENT")]
+    require_indent: bool,
+
+    /// Add capitalization and punctuation to generated words
+    #[arg(long, env = "TTYPER_PUNCTUATION")]
+    punctuation: bool,
+
+    /// Specify chance of a word being followed by punctuation mid-sentence
+    #[arg(
+        long,
+        value_name = "RATE",
+        default_value = "0.15",
+        value_parser = parse_rate,
+        env = "TTYPER_PUNCTUATION_RATE"
+    )]
+    punctuation_rate: f64,
+
+    /// Mix numbers into generated words
+    #[arg(long, env = "TTYPER_NUMBERS")]
+    numbers: bool,
+
+    /// Specify chance of a word being replaced with a number
+    #[arg(
+        long,
+        value_name = "RATE",
+        default_value = "0.1",
+        value_parser = parse_rate,
+        env = "TTYPER_NUMBERS_RATE"
+    )]
+    numbers_rate: f64,
+
+    /// Generate the test from a seed, so it can be reproduced
+    #[arg(long, value_name = "N", env = "TTYPER_SEED")]
+    seed: Option<u64>,
+
+    /// Race a ghost typing at the given words per minute
+    #[arg(
+        long,
+        value_name = "WPM",
+        value_parser = parse_positive,
+        env = "TTYPER_PACE"
+    )]
+    pace: Option<f64>,
+
+    /// Race a ghost replaying a test recorded with --record, on the same text
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["contents", "quote", "code", "pace"])]
+    ghost: Option<PathBuf>,
+
+    /// Disable backtracking to completed words
+    #[arg(long, env = "TTYPER_NO_BACKTRACK")]
     no_backtrack: bool,
 
     /// Enable sudden death mode to restart on first error
-    #[arg(long)]
+    #[arg(long, env = "TTYPER_SUDDEN_DEATH")]
     sudden_death: bool,
 
     /// Disable backspace
-    #[arg(long)]
+    #[arg(long, env = "TTYPER_NO_BACKSPACE")]
     no_backspace: bool,
 
+    /// How mistakes are handled: normal, stop-on-letter rejects wrong keys, stop-on-word won't
+    /// move on from a wrong word and confidence disables backspace, still recording presses of it
+    #[arg(long, value_name = "POLICY", default_value_t, env = "TTYPER_ERRORS")]
+    errors: ErrorPolicy,
+
+    /// Write results to the specified file when a test finishes
+    #[arg(short, long, value_name = "PATH", env = "TTYPER_OUTPUT")]
+    output: Option<PathBuf>,
+
+    /// Specify results file format [default: guessed from output path, or json]
+    #[arg(long, value_name = "FORMAT", env = "TTYPER_FORMAT")]
+    format: Option<export::Format>,
+
+    /// Don't save results to the test history
+    #[arg(long, env = "TTYPER_NO_HISTORY")]
+    no_history: bool,
+
+    /// Save a recording of each finished test to the specified file, to play back with `replay`
+    #[arg(long, value_name = "PATH", env = "TTYPER_RECORD")]
+    record: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
 
+/// Let flags which can be set in the environment take an optional value, so that `--flag=false`
+/// can turn off one enabled there or in the config
+fn bool_flag(arg: Arg) -> Arg {
+    if arg.get_env().is_none() || !matches!(arg.get_action(), ArgAction::SetTrue) {
+        return arg;
+    }
+    arg.value_name("BOOL")
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("true")
+        .default_value("false")
+        .action(ArgAction::Set)
+        .value_parser(BoolishValueParser::new())
+}
+
+fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.parse() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        _ => Err(String::from("rate must be a number between 0 and 1")),
+    }
+}
+
+fn parse_positive(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(String::from("value must be a positive number")),
+    }
+}
+
 #[derive(Debug, Subcommand)]
 enum Command {
     /// Generate shell completions
@@ -85,146 +277,666 @@ enum Command {
         /// Shell to generate completions for
         shell: Shell,
     },
+
+    /// Show and summarize past test results
+    History {
+        /// Only show tests in the specified language
+        #[arg(short, long, value_name = "LANG")]
+        language: Option<String>,
+
+        /// Only show tests taken on or after the specified date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        since: Option<history::Date>,
+
+        /// Only show tests taken on or before the specified date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        until: Option<history::Date>,
+
+        /// Only show the N most recent tests
+        #[arg(short = 'n', long, value_name = "N")]
+        limit: Option<usize>,
+    },
+
+    /// Host a race on the local network, on a test generated from the options given before `host`
+    Host {
+        /// Port to listen on
+        #[arg(short, long, default_value_t = multiplayer::DEFAULT_PORT)]
+        port: u16,
+
+        /// Name shown to other players
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+
+    /// Join a race hosted with `ttyper host`
+    Join {
+        /// Address of the host, followed by the port if it isn't the default
+        #[arg(value_name = "ADDRESS")]
+        addr: String,
+
+        /// Name shown to other players
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+
+    /// Play back a test recorded with --record
+    Replay {
+        /// Recording to play back
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Playback speed, e.g. 2 for twice as fast
+        #[arg(short, long, default_value = "1", value_parser = parse_positive)]
+        speed: f64,
+    },
+
+    /// Check or print the config
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Report unknown keys and invalid values in the config files and installed themes
+    Check,
+
+    /// Print the effective config, including defaults, after combining every layer
+    Dump,
 }
 
 impl Opt {
-    fn gen_contents(&self) -> Option<Vec<String>> {
+    /// Generate test contents, along with their attribution if they're a quote
+    fn gen_contents(&self, rng: &mut ChaCha8Rng) -> error::Result<(Vec<String>, Option<String>)> {
+        let (words, attribution) = match self.quote {
+            Some(selector) => {
+                let quote = self.gen_quote(selector, rng)?;
+                (quote.words(), Some(quote.attribution()))
+            }
+            None => (self.gen_unattributed_contents(rng)?, None),
+        };
+
+        if words.iter().all(|word| word.trim().is_empty()) {
+            return Err(Error::EmptyContents);
+        }
+        Ok((words, attribution))
+    }
+
+    /// Generate test contents other than quotes
+    fn gen_unattributed_contents(&self, rng: &mut ChaCha8Rng) -> error::Result<Vec<String>> {
         match &self.contents {
+            Some(path) if path.as_os_str() == "-" => Ok(std::io::stdin()
+                .lock()
+                .lines()
+                .map_while(Result::ok)
+                .collect()),
             Some(path) => {
-                let lines: Vec<String> = if path.as_os_str() == "-" {
-                    std::io::stdin()
-                        .lock()
-                        .lines()
-                        .map_while(Result::ok)
-                        .collect()
-                } else {
-                    let file = fs::File::open(path).expect("Error reading language file.");
-                    io::BufReader::new(file)
-                        .lines()
-                        .map_while(Result::ok)
-                        .collect()
-                };
-
-                Some(lines.iter().map(String::from).collect())
+                let text = read_text(path)?.ok_or_else(|| Error::Io {
+                    path: path.clone(),
+                    source: io::ErrorKind::NotFound.into(),
+                })?;
+                Ok(text.lines().map(String::from).collect())
             }
-            None => {
-                let lang_name = self
-                    .language
-                    .clone()
-                    .unwrap_or_else(|| self.config().default_language);
-
-                let bytes: Vec<u8> = self
-                    .language_file
-                    .as_ref()
-                    .map(fs::read)
-                    .and_then(Result::ok)
-                    .or_else(|| fs::read(self.language_dir().join(&lang_name)).ok())
-                    .or_else(|| {
-                        Resources::get(&format!("language/{}", &lang_name))
-                            .map(|f| f.data.into_owned())
-                    })?;
-
-                let mut rng = thread_rng();
-
-                let mut language: Vec<&str> = str::from_utf8(&bytes)
-                    .expect("Language file had non-utf8 encoding.")
-                    .lines()
-                    .collect();
-                language.shuffle(&mut rng);
-
-                let mut contents: Vec<_> = language
-                    .into_iter()
-                    .cycle()
-                    .take(self.words.get())
-                    .map(ToOwned::to_owned)
-                    .collect();
-                contents.shuffle(&mut rng);
+            None if self.code => self.gen_snippet(rng),
+            None if self.time.is_some() => self.gen_words(TIMED_BATCH, rng),
+            None => self.gen_words(self.words.get(), rng),
+        }
+    }
+
+    /// Seed a test was generated from, if `--seed` with it reproduces the test. Tests of files,
+    /// recordings and practice words aren't generated from their seed alone.
+    fn reproducible_seed(
+        &self,
+        ghost: Option<&Recording>,
+        practice: bool,
+        seed: u64,
+    ) -> Option<u64> {
+        (ghost.is_none() && self.contents.is_none() && !practice).then_some(seed)
+    }
+
+    /// Whether the test is made of words generated from a language, rather than fixed contents
+    fn generates_words(&self) -> bool {
+        self.contents.is_none() && self.quote.is_none() && !self.code
+    }
+
+    /// Name of the test language, which is set from the config at startup if not given
+    fn language_name(&self) -> String {
+        self.language
+            .clone()
+            .unwrap_or_else(|| Config::default().default_language)
+    }
+
+    /// Load the word list of the configured language
+    fn language_words(&self) -> error::Result<Vec<String>> {
+        let lang_name = self.language_name();
+
+        let text = match &self.language_file {
+            Some(path) => read_text(path)?.ok_or_else(|| Error::Io {
+                path: path.clone(),
+                source: io::ErrorKind::NotFound.into(),
+            })?,
+            None => self
+                .read_runtime_file("language", &lang_name)?
+                .ok_or_else(|| {
+                    Error::unknown_language(&lang_name, "words", self.language_names())
+                })?,
+        };
+
+        Ok(text.lines().map(ToOwned::to_owned).collect())
+    }
+
+    /// Generate random words from the configured language
+    fn gen_words(&self, count: usize, rng: &mut ChaCha8Rng) -> error::Result<Vec<String>> {
+        let mut language = self.language_words()?;
+        language.shuffle(rng);
 
-                Some(contents)
+        let mut contents: Vec<_> = language.into_iter().cycle().take(count).collect();
+        contents.shuffle(rng);
+
+        if self.numbers {
+            generate::insert_numbers(&mut contents, self.numbers_rate, rng);
+        }
+        if self.punctuation {
+            let rules = generate::Rules::for_language(&self.language_name());
+            generate::punctuate(&mut contents, rules, self.punctuation_rate, rng);
+        }
+
+        Ok(contents)
+    }
+
+    /// Choose a quote in the configured language
+    fn gen_quote(
+        &self,
+        selector: quote::Selector,
+        rng: &mut ChaCha8Rng,
+    ) -> error::Result<quote::Quote> {
+        let lang_name = self.language_name();
+        // quotes are shared between word lists of the same language, e.g. english200 and english1000
+        let base_name = lang_name.trim_end_matches(|c: char| c.is_ascii_digit());
+
+        let mut found = None;
+        for name in [lang_name.as_str(), base_name] {
+            if let Some(text) = self.read_runtime_file("quote", name)? {
+                found = Some((name, text));
+                break;
             }
         }
+        let (name, text) = found.ok_or_else(|| {
+            Error::unknown_language(&lang_name, "quotes", self.runtime_names("quote"))
+        })?;
+
+        let quotes = quote::parse(&text)
+            .map_err(|err| Error::parse(self.runtime_path("quote", name), &text, err))?;
+
+        selector
+            .choose(&quotes, rng)
+            .cloned()
+            .ok_or(Error::NoMatchingQuote {
+                language: lang_name,
+            })
     }
 
-    /// Configuration
-    fn config(&self) -> Config {
-        fs::read(
-            self.config
-                .clone()
-                .unwrap_or_else(|| self.config_dir().join("config.toml")),
-        )
-        .map(|bytes| {
-            toml::from_str(str::from_utf8(&bytes).unwrap_or_default())
-                .expect("Configuration was ill-formed.")
-        })
-        .unwrap_or_default()
+    /// Choose a code snippet in the configured language
+    fn gen_snippet(&self, rng: &mut ChaCha8Rng) -> error::Result<Vec<String>> {
+        let lang_name = self.language_name();
+
+        let text = self.read_runtime_file("code", &lang_name)?.ok_or_else(|| {
+            Error::unknown_language(&lang_name, "code snippets", self.runtime_names("code"))
+        })?;
+
+        let snippets = snippet::parse(&text)
+            .map_err(|err| Error::parse(self.runtime_path("code", &lang_name), &text, err))?;
+
+        snippets
+            .choose(rng)
+            .map(|snippet| snippet.lines().map(String::from).collect())
+            .ok_or(Error::EmptyContents)
     }
 
-    /// Installed languages under config directory
-    fn languages(&self) -> io::Result<impl Iterator<Item = OsString>> {
+    /// Read a file from a directory under the config directory, falling back to the builtin
+    /// one of the same name
+    fn read_runtime_file(&self, dir: &str, name: &str) -> error::Result<Option<String>> {
+        if let Ok(config_dir) = self.config_dir() {
+            if let Some(text) = read_text(&config_dir.join(dir).join(name))? {
+                return Ok(Some(text));
+            }
+        }
+
+        match Resources::get(&format!("{}/{}", dir, name)) {
+            Some(file) => String::from_utf8(file.data.into_owned())
+                .map(Some)
+                .map_err(|_| Error::Encoding {
+                    path: self.runtime_path(dir, name),
+                }),
+            None => Ok(None),
+        }
+    }
+
+    /// Path of a runtime file for diagnostics, whether it was read from disk or is builtin
+    fn runtime_path(&self, dir: &str, name: &str) -> PathBuf {
+        self.config_dir()
+            .map(|config_dir| config_dir.join(dir).join(name))
+            .ok()
+            .filter(|path| path.exists())
+            .unwrap_or_else(|| Path::new("<builtin>").join(dir).join(name))
+    }
+
+    /// Names of the builtin and installed files in a runtime directory
+    fn runtime_names(&self, dir: &str) -> Vec<String> {
+        let prefix = format!("{}/", dir);
         let builtin = Resources::iter().filter_map(|name| {
-            name.strip_prefix("language/")
+            name.strip_prefix(prefix.as_str())
                 .map(ToOwned::to_owned)
                 .map(OsString::from)
         });
 
         let configured = self
-            .language_dir()
-            .read_dir()
+            .config_dir()
+            .map(|config_dir| config_dir.join(dir))
             .into_iter()
-            .flatten()
+            .flat_map(|dir| dir.read_dir().into_iter().flatten())
             .map_while(Result::ok)
             .map(|e| e.file_name());
 
-        Ok(builtin.chain(configured))
+        builtin
+            .chain(configured)
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Create a test of the given contents with the configured settings
+    fn new_test(&self, contents: Vec<String>) -> Test {
+        let mut test = Test::new(
+            contents,
+            !self.no_backtrack,
+            self.sudden_death,
+            !self.no_backspace,
+            self.time_limit(),
+        )
+        .with_error_policy(self.errors);
+
+        if self.code {
+            test = test.with_code(CodeOptions {
+                tab_width: self.tab_width.get(),
+                require_indent: self.require_indent,
+            });
+        }
+        if let Some(wpm) = self.pace {
+            test = test.with_ghost(Ghost::pace(wpm));
+        }
+        self.emulate(test)
+    }
+
+    /// Emulate the keyboard layout given with --layout in a test, if any
+    fn emulate(&self, test: Test) -> Test {
+        match &self.emulated {
+            Some(layout) => test.with_layout(layout.clone()),
+            None => test,
+        }
+    }
+
+    /// Generate a test and its attribution. When racing a recording, this is the recorded test.
+    fn gen_test(
+        &self,
+        ghost: Option<&Recording>,
+        rng: &mut ChaCha8Rng,
+    ) -> error::Result<(Test, Option<String>)> {
+        if let Some(recording) = ghost {
+            let test = self.emulate(recording.test());
+            return Ok((test.with_ghost(Ghost::from_recording(recording)), None));
+        }
+
+        let (contents, attribution) = self.gen_contents(rng)?;
+        Ok((self.new_test(contents), attribution))
+    }
+
+    /// Time limit of the test, if any
+    fn time_limit(&self) -> Option<Duration> {
+        self.time.map(|secs| Duration::from_secs(secs.get()))
+    }
+
+    /// Save a finished test to the history file
+    fn record_history(&self, test: &Test, results: &Results, practice: bool) -> error::Result<()> {
+        if self.no_history {
+            return Ok(());
+        }
+
+        let language = match (&self.contents, &self.language_file) {
+            (Some(path), _) | (None, Some(path)) => path.display().to_string(),
+            (None, None) => self.language_name(),
+        };
+        let mode = history::Mode {
+            time: test.time_limit.map(|limit| limit.as_secs()),
+            quote: self.quote.is_some() && !practice,
+            code: test.code.is_some(),
+            practice,
+            backtracking: test.backtracking_enabled,
+            sudden_death: test.sudden_death_enabled,
+            backspace: test.backspace_enabled,
+            errors: test.error_policy,
+        };
+        let word_count = test.words.iter().filter(|w| !w.events.is_empty()).count();
+
+        let path = self.history_file()?;
+        history::append(
+            &path,
+            &history::Record::new(results, language, mode, word_count),
+        )
+        .map_err(|source| Error::Io { path, source })
+    }
+
+    /// Compute results of a finished test, saving them to the history, output file and
+    /// recording. Files which couldn't be written are described in the notice returned with the
+    /// results.
+    fn finish(
+        &self,
+        test: &Test,
+        practice: bool,
+        attribution: Option<&str>,
+        seed: Option<u64>,
+        recorder: &Recorder,
+    ) -> (Results, Option<String>) {
+        let results = results_of(test, attribution, seed);
+
+        // failures to save results shouldn't interrupt the test, so they're reported instead
+        let mut failures = Vec::new();
+        let _ = self.record_history(test, &results, practice);
+        if let Some(path) = &self.output {
+            if let Err(err) = export::write_to_path(&results, self.export_format(), path) {
+                failures.push(format!(
+                    "Couldn't write results to {}: {}",
+                    path.display(),
+                    err
+                ));
+            }
+        }
+        if let Some(path) = &self.record {
+            if let Err(err) = fs::File::create(path)
+                .and_then(|file| recorder.finish(test).write(io::BufWriter::new(file)))
+            {
+                failures.push(format!(
+                    "Couldn't write recording to {}: {}",
+                    path.display(),
+                    err
+                ));
+            }
+        }
+
+        let notice = (!failures.is_empty()).then(|| failures.join("; "));
+        (results, notice)
+    }
+
+    /// Format to export results in
+    fn export_format(&self) -> export::Format {
+        self.format.unwrap_or_else(|| {
+            self.output
+                .as_deref()
+                .map_or(export::Format::Json, export::Format::from_path)
+        })
+    }
+
+    /// Export results to the output file, or a timestamped file in the current directory
+    fn export(&self, results: &Results) -> io::Result<PathBuf> {
+        let format = self.export_format();
+        let path = self.output.clone().unwrap_or_else(|| {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            PathBuf::from(format!(
+                "ttyper-results-{}.{}",
+                timestamp,
+                format.extension()
+            ))
+        });
+
+        export::write_to_path(results, format, &path)?;
+        Ok(path)
+    }
+
+    /// Path of the user's config file, which is the one given with --config if any
+    fn user_config(&self) -> Option<PathBuf> {
+        match &self.config {
+            Some(path) => Some(path.clone()),
+            None => self
+                .config_dir()
+                .ok()
+                .map(|config_dir| config_dir.join("config.toml")),
+        }
+    }
+
+    /// Load the config files of every layer, applying the options they set which weren't given
+    /// as arguments or environment variables
+    fn load_config(&mut self, matches: &ArgMatches) -> error::Result<(layers::Layers, Config)> {
+        let mut layers = layers::Layers::load(self.user_config())?;
+        if let Some(name) = self.profile.as_ref().filter(|_| !self.list_profiles) {
+            layers.select_profile(name)?;
+        }
+        if let Some(name) = self
+            .theme
+            .clone()
+            .or_else(|| layers.theme_name())
+            .filter(|_| !self.list_themes)
+        {
+            let (path, text) = self.theme_file(&name)?;
+            layers.set_theme(&name, path, &text)?;
+        }
+
+        let config: Config = layers.get()?;
+        layers.get::<options::Options>()?.apply(self, matches);
+        self.language
+            .get_or_insert_with(|| config.default_language.clone());
+        if let Some(name) = self.layout.as_ref().filter(|_| !self.list_layouts) {
+            self.emulated = Some(self.layout_file(name)?);
+        }
+        Ok((layers, config))
+    }
+
+    /// Report every problem in the config files and installed themes, along with its position
+    fn check_config(&mut self, matches: &ArgMatches) -> error::Result<()> {
+        // paths of the files to check, and whether each is a theme rather than a config
+        let mut files: Vec<(PathBuf, bool)> = layers::paths(self.user_config())
+            .into_iter()
+            .map(|(_, path)| (path, false))
+            .collect();
+        if let Ok(config_dir) = self.config_dir() {
+            let mut themes: Vec<PathBuf> = fs::read_dir(config_dir.join("themes"))
+                .into_iter()
+                .flatten()
+                .map_while(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect();
+            themes.sort();
+            files.extend(themes.into_iter().map(|path| (path, true)));
+        }
+
+        let mut checked = 0;
+        let mut problems = 0;
+        for (path, is_theme) in files {
+            let Some(text) = read_text(&path)? else {
+                continue;
+            };
+            checked += 1;
+            let found = match is_theme {
+                true => check::theme(&text),
+                false => check::config(&text),
+            };
+            for problem in found {
+                let (line, column) = error::line_column(&text, problem.span.start);
+                println!(
+                    "{}:{}:{}: {}",
+                    path.display(),
+                    line,
+                    column,
+                    problem.message
+                );
+                problems += 1;
+            }
+        }
+
+        // some problems only show once the files are combined, like missing profiles or themes
+        if problems == 0 {
+            if let Err(err) = self.load_config(matches) {
+                println!("{}", err);
+                problems += 1;
+            }
+        }
+
+        if problems > 0 {
+            return Err(Error::InvalidConfig { problems });
+        }
+        println!("no problems found in {} files", checked);
+        Ok(())
+    }
+
+    /// Path and contents of a preset or installed theme
+    fn theme_file(&self, name: &str) -> error::Result<(PathBuf, String)> {
+        self.toml_file("themes", "theme", name)
+    }
+
+    /// Load a preset or installed keyboard layout
+    fn layout_file(&self, name: &str) -> error::Result<layout::Layout> {
+        let (path, text) = self.toml_file("layouts", "layout", name)?;
+        toml::from_str(&text).map_err(|err| Error::parse(path, &text, err))
+    }
+
+    /// Path and contents of a TOML file in a runtime directory, by its name without the extension
+    fn toml_file(
+        &self,
+        dir: &str,
+        kind: &'static str,
+        name: &str,
+    ) -> error::Result<(PathBuf, String)> {
+        let file_name = format!("{}.toml", name);
+        let text = self
+            .read_runtime_file(dir, &file_name)?
+            .ok_or_else(|| Error::unknown(kind, name, self.toml_names(dir)))?;
+        Ok((self.runtime_path(dir, &file_name), text))
+    }
+
+    /// Names of the TOML files in a runtime directory, without their extension
+    fn toml_names(&self, dir: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .runtime_names(dir)
+            .into_iter()
+            .filter_map(|name| name.strip_suffix(".toml").map(ToOwned::to_owned))
+            .collect();
+        // builtin files are also installed to the config directory
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Names of the builtin and installed languages
+    fn language_names(&self) -> Vec<String> {
+        self.runtime_names("language")
     }
 
     /// Config directory
-    fn config_dir(&self) -> PathBuf {
+    fn config_dir(&self) -> error::Result<PathBuf> {
         dirs::config_dir()
-            .expect("Failed to find config directory.")
-            .join("ttyper")
+            .map(|dir| dir.join("ttyper"))
+            .ok_or(Error::NoConfigDir)
     }
 
-    /// Language directory under config directory
-    fn language_dir(&self) -> PathBuf {
-        self.config_dir().join("language")
+    /// History file under data directory
+    fn history_file(&self) -> error::Result<PathBuf> {
+        let dir = match dirs::data_dir() {
+            Some(dir) => dir.join("ttyper"),
+            None => self.config_dir()?,
+        };
+        Ok(dir.join("history.jsonl"))
     }
 }
 
 enum State {
     Test(Test),
-    Results(Results),
+    /// Results of a finished test, which is kept so it can be taken again on the same text
+    Results(Results, Box<Test>),
 }
 
 impl State {
+    /// Show the results of the test in progress
+    fn finished(self, results: Results) -> Self {
+        match self {
+            State::Test(test) => State::Results(results, Box::new(test)),
+            state => state,
+        }
+    }
+
+    /// The test again from the start, on the same text
+    fn restarted(self) -> Self {
+        let mut test = match self {
+            State::Test(test) => test,
+            State::Results(_, test) => *test,
+        };
+        test.reset();
+        State::Test(test)
+    }
+
     fn render_into<B: ratatui::backend::Backend>(
         &self,
         terminal: &mut Terminal<B>,
         config: &Config,
+        emulated: Option<&layout::Layout>,
+        view: ui::ResultsView,
+        notice: Option<&str>,
     ) -> io::Result<()> {
         match self {
             State::Test(test) => {
-                terminal.draw(|f| {
-                    let area = f.size();
-                    f.render_widget(config.theme.apply_to(test), area);
-
-                    // Position cursor at end of input for IME composition support
-                    let chunks = Layout::default()
-                        .direction(Direction::Vertical)
-                        .constraints([Constraint::Length(3), Constraint::Length(6)])
-                        .split(area);
-                    let inner_x = chunks[0].x + 1;
-                    let inner_y = chunks[0].y + 1;
-                    let progress_width =
-                        ratatui::text::Line::from(test.words[test.current_word].progress.as_str())
-                            .width() as u16;
-                    let max_cursor_x = chunks[0].right().saturating_sub(2);
-                    f.set_cursor((inner_x + progress_width).min(max_cursor_x), inner_y);
-                })?;
+                terminal.draw(|f| draw_test(f, f.size(), test, config))?;
             }
-            State::Results(results) => {
+            State::Results(results, _) => {
                 terminal.draw(|f| {
-                    f.render_widget(config.theme.apply_to(results), f.size());
+                    match view {
+                        ui::ResultsView::Overview => {
+                            f.render_widget(config.theme.apply_to(results), f.size())
+                        }
+                        ui::ResultsView::Latency => f.render_widget(
+                            config.theme.apply_to(ui::LatencyResults(results)),
+                            f.size(),
+                        ),
+                        ui::ResultsView::Keyboard(metric) => f.render_widget(
+                            config.theme.apply_to(ui::KeyboardResults {
+                                results,
+                                rows: keyboard_rows(config, emulated),
+                                metric,
+                                keys: &config.keys,
+                            }),
+                            f.size(),
+                        ),
+                    }
+
+                    let area = f.size();
+                    f.render_widget(
+                        config.theme.apply_to(ui::Controls {
+                            keys: &config.keys,
+                            actions: &ui::results_controls(results),
+                        }),
+                        Rect::new(
+                            area.x,
+                            area.bottom().saturating_sub(1),
+                            area.width,
+                            1.min(area.height),
+                        ),
+                    );
+
+                    if let Some(notice) = notice {
+                        let area = f.size();
+                        let width = (notice.chars().count() as u16).min(area.width);
+                        f.render_widget(
+                            ratatui::widgets::Paragraph::new(notice)
+                                .style(config.theme.results_restart_prompt),
+                            ratatui::layout::Rect::new(
+                                area.right() - width,
+                                area.bottom().saturating_sub(1),
+                                width,
+                                1.min(area.height),
+                            ),
+                        );
+                    }
                 })?;
             }
         }
@@ -232,113 +944,457 @@ impl State {
     }
 }
 
-fn main() -> io::Result<()> {
-    let opt = Opt::parse();
-    if opt.debug {
-        dbg!(&opt);
+/// Draw a test in progress, with the HUD and keyboard if they're enabled
+fn draw_test(f: &mut Frame, mut area: Rect, test: &Test, config: &Config) {
+    if config.show_hud {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(area);
+        f.render_widget(config.theme.apply_to(ui::Hud(test)), chunks[0]);
+        area = chunks[1];
+    }
+    if config.show_keyboard {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(ui::KEYBOARD_HEIGHT)])
+            .split(area);
+        f.render_widget(
+            config.theme.apply_to(ui::Keyboard {
+                rows: keyboard_rows(config, test.layout.as_ref()),
+                heat: None,
+                next_key: test.next_char(),
+            }),
+            chunks[1],
+        );
+        area = chunks[0];
+    }
+    f.render_widget(config.theme.apply_to(test), area);
+
+    // Position cursor at end of input for IME composition support
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Length(6)])
+        .split(area);
+    let inner_x = chunks[0].x + 1;
+    let inner_y = chunks[0].y + 1;
+    let progress_width =
+        ratatui::text::Line::from(test.words[test.current_word].progress.as_str()).width() as u16;
+    let max_cursor_x = chunks[0].right().saturating_sub(2);
+    f.set_cursor((inner_x + progress_width).min(max_cursor_x), inner_y);
+}
+
+/// Rows of the on-screen keyboard, which shows the emulated layout if there is one
+fn keyboard_rows<'a>(config: &Config, emulated: Option<&'a layout::Layout>) -> [&'a str; 4] {
+    emulated.map_or(config.keyboard_layout.rows(), layout::Layout::rows)
+}
+
+/// Compute results of a test, labelled with how it was generated
+fn results_of(test: &Test, attribution: Option<&str>, seed: Option<u64>) -> Results {
+    let mut results = Results::from(test);
+    results.attribution = attribution.map(String::from);
+    results.seed = seed;
+    results
+}
+
+/// Seed of a test following the test with the given seed, e.g. after restarting it
+fn derive_seed(seed: u64, stream: u64) -> u64 {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(stream);
+    rng.gen()
+}
+
+/// Read a text file, treating a missing file as `None`
+fn read_text(path: &Path) -> error::Result<Option<String>> {
+    match fs::read(path) {
+        Ok(bytes) => String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|_| Error::Encoding {
+                path: path.to_owned(),
+            }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(source) => Err(Error::Io {
+            path: path.to_owned(),
+            source,
+        }),
+    }
+}
+
+/// Read a recording made with --record
+fn read_recording(path: &Path) -> error::Result<Recording> {
+    let file = fs::File::open(path).map_err(|source| Error::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    Recording::read(io::BufReader::new(file)).map_err(|err| Error::json(path.to_owned(), err))
+}
+
+/// Leave raw mode and the alternate screen, ignoring failures since this is done on the way out
+fn restore_terminal() {
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        cursor::RestorePosition,
+        cursor::Show,
+        terminal::LeaveAlternateScreen,
+    );
+}
+
+/// Restores the terminal when dropped, so it's also restored when returning early with an error
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> error::Result<Self> {
+        // restore the terminal before a panic message is printed, so it isn't lost
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            default_hook(info);
+        }));
+
+        terminal::enable_raw_mode()?;
+        execute!(
+            io::stdout(),
+            cursor::Hide,
+            cursor::SavePosition,
+            terminal::EnterAlternateScreen,
+        )?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Play back a recorded test, then show its results
+fn replay(config: &Config, path: &Path, speed: f64) -> error::Result<()> {
+    let recording = read_recording(path)?;
+
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+    let _guard = TerminalGuard::enter()?;
+    terminal.clear()?;
+
+    let mut state = State::Test(recording.test());
+    let mut keys = recording.keys.iter().peekable();
+    let mut view = ui::ResultsView::default();
+    let notice = format!("Replay of {}", path.display());
+
+    let origin = Instant::now();
+    // point in the recording at which the test started, which moves when sudden death restarts it
+    let mut test_start = Duration::ZERO;
+    loop {
+        if let State::Test(ref mut test) = state {
+            let position = origin.elapsed().mul_f64(speed);
+            while let Some(key) = keys.next_if(|key| key.time <= position.as_secs_f64()) {
+                let time = Duration::from_secs_f64(key.time);
+                if test.start.is_none() {
+                    test_start = time;
+                }
+                key.replay(test, origin + time);
+            }
+            // keep the test's clock on playback time, so the HUD and time limit follow it
+            if test.start.is_some() {
+                test.start = Instant::now().checked_sub(position.saturating_sub(test_start));
+            }
+
+            let time_up = test
+                .deadline()
+                .is_some_and(|deadline| Instant::now() >= deadline);
+            if test.complete || time_up || (keys.peek().is_none() && test.time_limit.is_none()) {
+                let results = Results::from(&*test);
+                state = state.finished(results);
+            }
+        }
+
+        state.render_into(&mut terminal, config, None, view, Some(&notice))?;
+
+        if let State::Test(_) = state {
+            let due = keys.peek().map_or(HUD_TICK, |key| {
+                Duration::from_secs_f64(key.time)
+                    .div_f64(speed)
+                    .saturating_sub(origin.elapsed())
+            });
+            if !event::poll(due.min(HUD_TICK))? {
+                continue;
+            }
+        }
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        // nothing is typed during a replay, so it takes the keys of the results screen throughout
+        match config.keys.action(Screen::Results, key) {
+            Some(Action::Quit) => break,
+            Some(Action::NextView) if matches!(state, State::Results(..)) => view = view.next(),
+            Some(Action::NextMetric) => {
+                if let ui::ResultsView::Keyboard(metric) = view {
+                    view = ui::ResultsView::Keyboard(metric.next());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn run() -> error::Result<()> {
+    let matches = Opt::command().get_matches();
+    let mut opt = Opt::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    // checking the config has to work even when it can't be loaded
+    if let Some(Command::Config {
+        command: ConfigCommand::Check,
+    }) = opt.command
+    {
+        return opt.check_config(&matches);
     }
 
-    let config = opt.config();
+    let (layers, config) = opt.load_config(&matches)?;
     if opt.debug {
-        dbg!(&config);
+        options::print_effective(&opt, &matches, &layers, &config);
     }
 
-    if let Some(Command::Completions { shell }) = opt.command {
-        generate(shell, &mut Opt::command(), "ttyper", &mut io::stdout());
-        return Ok(());
+    match opt.command {
+        Some(Command::Completions { shell }) => {
+            generate(shell, &mut Opt::command(), "ttyper", &mut io::stdout());
+            return Ok(());
+        }
+        Some(Command::History {
+            ref language,
+            since,
+            until,
+            limit,
+        }) => {
+            let path = opt.history_file()?;
+            let (records, skipped) = history::load(&path).map_err(|source| Error::Io {
+                path: path.clone(),
+                source,
+            })?;
+            if skipped > 0 {
+                eprintln!(
+                    "warning: skipped {} unreadable line(s) of {}",
+                    skipped,
+                    path.display()
+                );
+            }
+            let mut records: Vec<_> = records
+                .into_iter()
+                .filter(|r| language.as_ref().is_none_or(|l| &r.language == l))
+                .filter(|r| since.is_none_or(|d| r.date() >= d))
+                .filter(|r| until.is_none_or(|d| r.date() <= d))
+                .collect();
+            if let Some(limit) = limit {
+                records.drain(..records.len().saturating_sub(limit));
+            }
+
+            history::print_summary(&records);
+            return Ok(());
+        }
+        Some(Command::Host { port, ref name }) => {
+            let name = multiplayer::player_name(name.clone());
+            let peer = multiplayer::Peer::host(port, name).map_err(|source| Error::Network {
+                addr: format!("port {}", port),
+                source,
+            })?;
+            return multiplayer::run(&opt, &config, peer);
+        }
+        Some(Command::Join { ref addr, ref name }) => {
+            let name = multiplayer::player_name(name.clone());
+            let peer = multiplayer::Peer::join(addr, name).map_err(|source| Error::Network {
+                addr: addr.clone(),
+                source,
+            })?;
+            return multiplayer::run(&opt, &config, peer);
+        }
+        Some(Command::Replay { ref path, speed }) => return replay(&config, path, speed),
+        Some(Command::Config { ref command }) => {
+            match command {
+                ConfigCommand::Check => unreachable!("the config is checked before it's loaded"),
+                ConfigCommand::Dump => print!("{}", options::dump(&opt, &config)),
+            }
+            return Ok(());
+        }
+        None => {}
     }
 
     if opt.list_languages {
-        opt.languages()
-            .unwrap()
-            .for_each(|name| println!("{}", name.to_str().expect("Ill-formatted language name.")));
+        opt.language_names()
+            .iter()
+            .for_each(|name| println!("{}", name));
 
         return Ok(());
     }
 
-    let backend = CrosstermBackend::new(io::stdout());
-    let mut terminal = Terminal::new(backend)?;
-    let contents = opt
-        .gen_contents()
-        .expect("Couldn't get test contents. Make sure the specified language actually exists.");
+    if opt.list_themes {
+        opt.toml_names("themes")
+            .iter()
+            .for_each(|name| println!("{}", name));
 
-    if contents.is_empty() {
-        panic!("Empty test contents.");
+        return Ok(());
     }
 
-    terminal::enable_raw_mode()?;
-    execute!(
-        io::stdout(),
-        cursor::Hide,
-        cursor::SavePosition,
-        terminal::EnterAlternateScreen,
-    )?;
+    if opt.list_layouts {
+        opt.toml_names("layouts")
+            .iter()
+            .for_each(|name| println!("{}", name));
+
+        return Ok(());
+    }
+
+    if opt.list_profiles {
+        config.profile.keys().for_each(|name| println!("{}", name));
+
+        return Ok(());
+    }
+
+    let mut seed = opt.seed.unwrap_or_else(|| thread_rng().gen());
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let ghost = opt.ghost.as_deref().map(read_recording).transpose()?;
+    let (test, mut attribution) = opt.gen_test(ghost.as_ref(), &mut rng)?;
+
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+    let _guard = TerminalGuard::enter()?;
     terminal.clear()?;
 
-    let mut state = State::Test(Test::new(
-        contents,
-        !opt.no_backtrack,
-        opt.sudden_death,
-        !opt.no_backspace,
-    ));
+    let mut state = State::Test(test);
+
+    let mut practice = false;
+    let mut recorder = Recorder::default();
+    let mut notice: Option<String> = None;
+    let mut view = ui::ResultsView::default();
 
-    state.render_into(&mut terminal, &config)?;
+    state.render_into(
+        &mut terminal,
+        &config,
+        opt.emulated.as_ref(),
+        view,
+        notice.as_deref(),
+    )?;
     loop {
-        let event = event::read()?;
-
-        // handle exit controls
-        match event {
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('c'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::CONTROL,
-                ..
-            }) => break,
-            Event::Key(KeyEvent {
-                code: KeyCode::Esc,
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            }) => match state {
-                State::Test(ref test) => {
-                    state = State::Results(Results::from(test));
+        let event = match state {
+            State::Test(ref test) => {
+                let deadline = test.deadline();
+                let tick = match test.ghost {
+                    Some(_) => Some(GHOST_TICK),
+                    None => config.show_hud.then_some(HUD_TICK),
                 }
-                State::Results(_) => break,
-            },
-            _ => {}
-        }
+                .filter(|_| test.start.is_some());
+                let timeout = deadline
+                    .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+                    .into_iter()
+                    .chain(tick)
+                    .min();
+
+                match timeout {
+                    Some(timeout) if !event::poll(timeout)? => {
+                        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                            // time's up
+                            let seed = opt.reproducible_seed(ghost.as_ref(), practice, seed);
+                            let (results, failure) =
+                                opt.finish(test, practice, attribution.as_deref(), seed, &recorder);
+                            notice = failure;
+                            state = state.finished(results);
+                        }
+                        state.render_into(
+                            &mut terminal,
+                            &config,
+                            opt.emulated.as_ref(),
+                            view,
+                            notice.as_deref(),
+                        )?;
+                        continue;
+                    }
+                    _ => event::read()?,
+                }
+            }
+            State::Results(..) => event::read()?,
+        };
+        notice = None;
+
+        let key = match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            Event::Key(_) => continue,
+            _ => {
+                state.render_into(
+                    &mut terminal,
+                    &config,
+                    opt.emulated.as_ref(),
+                    view,
+                    notice.as_deref(),
+                )?;
+                continue;
+            }
+        };
+        let action = match state {
+            State::Test(ref test) => config.keys.action(Screen::of(test), key),
+            State::Results(..) => config.keys.action(Screen::Results, key),
+        };
 
         match state {
+            _ if action == Some(Action::Quit) => break,
+            _ if matches!(action, Some(Action::Restart | Action::QuickRestart)) => {
+                seed = derive_seed(seed, RESTART_STREAM);
+                rng = ChaCha8Rng::seed_from_u64(seed);
+                let (test, new_attribution) = opt.gen_test(ghost.as_ref(), &mut rng)?;
+                practice = false;
+                attribution = new_attribution;
+                recorder = Recorder::default();
+                state = State::Test(test);
+            }
+            _ if action == Some(Action::RestartSameText) => {
+                recorder = Recorder::default();
+                state = state.restarted();
+            }
+            State::Test(ref test) if action == Some(Action::AbortToResults) => {
+                let seed = opt.reproducible_seed(ghost.as_ref(), practice, seed);
+                let results = results_of(test, attribution.as_deref(), seed);
+                state = state.finished(results);
+            }
             State::Test(ref mut test) => {
-                if let Event::Key(key) = event {
+                if action == Some(Action::DeleteWord) {
+                    recorder.record(key, action);
+                    test.delete_word(key);
+                } else {
+                    // recordings hold the keys as typed in the emulated layout, so they replay
+                    // the same without it
+                    recorder.record(test.remap(key), None);
                     test.handle_key(key);
-                    if test.complete {
-                        state = State::Results(Results::from(&*test));
-                    }
                 }
-            }
-            State::Results(ref result) => match event {
-                Event::Key(KeyEvent {
-                    code: KeyCode::Char('r'),
-                    kind: KeyEventKind::Press,
-                    modifiers: KeyModifiers::NONE,
-                    ..
-                }) => {
-                    state = State::Test(Test::new(
-                        opt.gen_contents().expect(
-                            "Couldn't get test contents. Make sure the specified language actually exists.",
-                        ),
-                        !opt.no_backtrack,
-                        opt.sudden_death,
-                        !opt.no_backspace,
-                    ));
+                if test.time_limit.is_some()
+                    && opt.generates_words()
+                    && test.words.len() - test.current_word < TIMED_BATCH
+                {
+                    test.extend(opt.gen_words(TIMED_BATCH, &mut rng).unwrap_or_default());
+                }
+                if test.complete {
+                    let seed = opt.reproducible_seed(ghost.as_ref(), practice, seed);
+                    let (results, failure) =
+                        opt.finish(test, practice, attribution.as_deref(), seed, &recorder);
+                    notice = failure;
+                    state = state.finished(results);
                 }
-                Event::Key(KeyEvent {
-                    code: KeyCode::Char('p'),
-                    kind: KeyEventKind::Press,
-                    modifiers: KeyModifiers::NONE,
-                    ..
-                }) => {
+            }
+            State::Results(ref result, _) => match action {
+                Some(Action::Practice) => {
                     if result.missed_words.is_empty() {
                         continue;
                     }
@@ -347,34 +1403,167 @@ fn main() -> io::Result<()> {
                         .iter()
                         .flat_map(|w| vec![w.clone(); 5])
                         .collect();
-                    practice_words.shuffle(&mut thread_rng());
-                    state = State::Test(Test::new(
-                        practice_words,
-                        !opt.no_backtrack,
-                        opt.sudden_death,
-                        !opt.no_backspace,
-                    ));
+                    seed = derive_seed(seed, PRACTICE_STREAM);
+                    rng = ChaCha8Rng::seed_from_u64(seed);
+                    practice_words.shuffle(&mut rng);
+                    practice = true;
+                    attribution = None;
+                    recorder = Recorder::default();
+                    state = State::Test(
+                        opt.emulate(
+                            Test::new(
+                                practice_words,
+                                !opt.no_backtrack,
+                                opt.sudden_death,
+                                !opt.no_backspace,
+                                None,
+                            )
+                            .with_error_policy(opt.errors),
+                        ),
+                    );
+                }
+                Some(Action::Drill) => {
+                    let weaknesses = practice::Weaknesses::from_results(result);
+                    seed = derive_seed(seed, DRILL_STREAM);
+                    rng = ChaCha8Rng::seed_from_u64(seed);
+                    let drill_words = opt
+                        .language_words()
+                        .map(|language| {
+                            practice::drill(&weaknesses, &language, opt.words.get(), &mut rng)
+                        })
+                        .unwrap_or_default();
+                    if drill_words.is_empty() {
+                        continue;
+                    }
+                    practice = true;
+                    attribution = None;
+                    recorder = Recorder::default();
+                    state = State::Test(
+                        opt.emulate(
+                            Test::new(
+                                drill_words,
+                                !opt.no_backtrack,
+                                opt.sudden_death,
+                                !opt.no_backspace,
+                                None,
+                            )
+                            .with_error_policy(opt.errors),
+                        ),
+                    );
+                }
+                Some(Action::NextView) => view = view.next(),
+                Some(Action::NextMetric) => {
+                    if let ui::ResultsView::Keyboard(metric) = view {
+                        view = ui::ResultsView::Keyboard(metric.next());
+                    }
+                }
+                Some(Action::Export) => {
+                    notice = Some(match opt.export(result) {
+                        Ok(path) => format!("Exported results to {}", path.display()),
+                        Err(err) => format!("Couldn't export results: {}", err),
+                    });
+                    state.render_into(
+                        &mut terminal,
+                        &config,
+                        opt.emulated.as_ref(),
+                        view,
+                        notice.as_deref(),
+                    )?;
+                    continue;
                 }
-                Event::Key(KeyEvent {
-                    code: KeyCode::Char('q'),
-                    kind: KeyEventKind::Press,
-                    modifiers: KeyModifiers::NONE,
-                    ..
-                }) => break,
                 _ => {}
             },
         }
 
-        state.render_into(&mut terminal, &config)?;
+        state.render_into(
+            &mut terminal,
+            &config,
+            opt.emulated.as_ref(),
+            view,
+            notice.as_deref(),
+        )?;
     }
 
-    terminal::disable_raw_mode()?;
-    execute!(
-        io::stdout(),
-        cursor::RestorePosition,
-        cursor::Show,
-        terminal::LeaveAlternateScreen,
-    )?;
-
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gen(args: &[&str], seed: u64) -> Vec<String> {
+        let opt = Opt::parse_from(["ttyper"].iter().chain(args));
+        let (words, _) = opt
+            .gen_contents(&mut ChaCha8Rng::seed_from_u64(seed))
+            .unwrap();
+        words
+    }
+
+    #[test]
+    fn seeds_reproduce_tests() {
+        for args in [
+            &["-l", "english200", "--punctuation", "--numbers"][..],
+            &["-l", "english", "-q"],
+            &["-l", "rust", "--code"],
+        ] {
+            assert_eq!(gen(args, 42), gen(args, 42));
+        }
+        assert_ne!(
+            gen(&["-l", "english200"], 42),
+            gen(&["-l", "english200"], 43)
+        );
+
+        assert_eq!(
+            derive_seed(42, RESTART_STREAM),
+            derive_seed(42, RESTART_STREAM)
+        );
+        assert_ne!(
+            derive_seed(42, RESTART_STREAM),
+            derive_seed(42, PRACTICE_STREAM)
+        );
+    }
+
+    #[test]
+    fn shows_seeds_only_of_generated_tests() {
+        let opt = Opt::parse_from(["ttyper"]);
+        assert_eq!(opt.reproducible_seed(None, false, 42), Some(42));
+        assert_eq!(opt.reproducible_seed(None, true, 42), None);
+
+        let opt = Opt::parse_from(["ttyper", "text.txt"]);
+        assert_eq!(opt.reproducible_seed(None, false, 42), None);
+    }
+
+    #[test]
+    fn theme_presets_set_every_style() {
+        let styles = toml::Table::try_from(ttyper::config::Theme::default()).unwrap();
+        let presets: Vec<_> = Resources::iter()
+            .filter(|name| name.starts_with("themes/"))
+            .collect();
+        assert!(presets.len() >= 6);
+
+        for name in presets {
+            let text = String::from_utf8(Resources::get(&name).unwrap().data.into_owned()).unwrap();
+            toml::from_str::<ttyper::config::Theme>(&text).unwrap();
+            let preset: toml::Table = text.parse().unwrap();
+            for key in styles.keys() {
+                assert!(preset.contains_key(key), "{} doesn't set {}", name, key);
+            }
+        }
+    }
+
+    #[test]
+    fn layout_presets_load() {
+        let load = |name: &str| {
+            let file = Resources::get(&format!("layouts/{}.toml", name)).unwrap();
+            toml::from_str::<layout::Layout>(str::from_utf8(&file.data).unwrap()).unwrap()
+        };
+        for name in Resources::iter().filter(|name| name.starts_with("layouts/")) {
+            load(&name["layouts/".len()..name.len() - ".toml".len()]);
+        }
+
+        use ttyper::config::KeyboardLayout;
+        assert_eq!(load("dvorak").rows(), KeyboardLayout::Dvorak.rows());
+        assert_eq!(load("colemak").rows(), KeyboardLayout::Colemak.rows());
+        assert_eq!(load("dvorak").remap_char('Q'), '"');
+    }
+}
@@ -0,0 +1,600 @@
+use crate::error::{self, Error};
+use crate::{derive_seed, draw_test, Opt, TerminalGuard, RESTART_STREAM};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    terminal::Terminal,
+    widgets::Paragraph,
+};
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+use ttyper::{
+    config::Config,
+    ghost,
+    keys::{Action, Screen},
+    race::{Message, Player, Progress},
+    test::{results::Results, Test},
+    ui,
+};
+
+/// Port races are hosted on unless another is given
+pub const DEFAULT_PORT: u16 = 7878;
+
+// Interval at which messages from other players are handled while no keys are pressed
+const NET_TICK: Duration = Duration::from_millis(50);
+
+// Interval at which progress is shared while no keys are pressed, so the board's speeds stay live
+const PROGRESS_TICK: Duration = Duration::from_millis(500);
+
+// Players can't generate more words for a timed race the way a single player test does, so
+// they're sent enough words up front for typing at this rate
+const TIMED_WORDS_PER_SECOND: u64 = 4;
+
+// Time the host waits to send a message to a player before dropping them, so one stalled
+// connection can't hold up the race for everyone else
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Longest line read from a connection, well beyond the size of any message
+const MAX_LINE_LEN: u64 = 1 << 20;
+
+/// Something which happened on a connection, passed from its thread to the race
+enum NetEvent {
+    Connected(usize, TcpStream),
+    Received(usize, Message),
+    Disconnected(usize),
+}
+
+/// Send a message as a line of JSON
+fn send(stream: &mut TcpStream, message: &Message) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
+
+/// Send a message to a player, closing the connection if it fails so its reader reports the
+/// player as disconnected
+fn send_or_close(stream: &mut TcpStream, message: &Message) -> bool {
+    let sent = send(stream, message).is_ok();
+    if !sent {
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+    sent
+}
+
+/// Read messages from a connection on a new thread until it's closed or sends something invalid
+fn spawn_reader(id: usize, stream: TcpStream, events: Sender<NetEvent>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            // an overlong line is cut off, so it fails to parse rather than filling memory
+            match (&mut reader).take(MAX_LINE_LEN).read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let Ok(message) = serde_json::from_str(&line) else {
+                break;
+            };
+            if events.send(NetEvent::Received(id, message)).is_err() {
+                return;
+            }
+        }
+        let _ = events.send(NetEvent::Disconnected(id));
+    });
+}
+
+enum Role {
+    /// Relays the progress of every player to the others
+    Host { streams: HashMap<usize, TcpStream> },
+    /// Joined a race through a connection to the host
+    Player { stream: TcpStream },
+}
+
+/// One of the players in a race, keeping track of all of them
+pub struct Peer {
+    role: Role,
+    events: Receiver<NetEvent>,
+    players: Vec<Player>,
+    /// Id of the local player
+    you: usize,
+    /// Address the host listens on or the player is connected to
+    addr: String,
+}
+
+impl Peer {
+    /// Host races on the given port of every interface, for players to join
+    pub fn host(port: u16, name: String) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let addr = listener.local_addr()?.to_string();
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            for (id, stream) in (1..).zip(listener.incoming()) {
+                let Ok((stream, reader)) = stream.and_then(|s| Ok((s.try_clone()?, s))) else {
+                    continue;
+                };
+                let _ = stream.set_nodelay(true);
+                let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                if sender.send(NetEvent::Connected(id, stream)).is_err() {
+                    return;
+                }
+                spawn_reader(id, reader, sender.clone());
+            }
+        });
+
+        Ok(Self {
+            role: Role::Host {
+                streams: HashMap::new(),
+            },
+            events,
+            players: vec![Player {
+                id: 0,
+                name,
+                progress: Progress::default(),
+            }],
+            you: 0,
+            addr,
+        })
+    }
+
+    /// Join a race hosted at the given address, which uses the default port if it has none
+    pub fn join(addr: &str, name: String) -> io::Result<Self> {
+        let addr = match addr.rsplit_once(':') {
+            Some((_, port)) if port.parse::<u16>().is_ok() => addr.to_owned(),
+            _ => format!("{}:{}", addr, DEFAULT_PORT),
+        };
+        let mut stream = TcpStream::connect(&addr)?;
+        stream.set_nodelay(true)?;
+        send(&mut stream, &Message::Join { name })?;
+
+        let (sender, events) = mpsc::channel();
+        spawn_reader(0, stream.try_clone()?, sender);
+        Ok(Self {
+            role: Role::Player { stream },
+            events,
+            players: Vec::new(),
+            // not known until the host welcomes us
+            you: usize::MAX,
+            addr,
+        })
+    }
+
+    fn is_host(&self) -> bool {
+        matches!(self.role, Role::Host { .. })
+    }
+
+    /// Handle the messages received since the last call, returning the test of a race the host
+    /// started
+    pub fn poll(&mut self) -> error::Result<Option<Test>> {
+        let mut changed = false;
+        let mut started = None;
+        while let Ok(event) = self.events.try_recv() {
+            match (&mut self.role, event) {
+                (Role::Host { streams }, NetEvent::Connected(id, stream)) => {
+                    streams.insert(id, stream);
+                }
+                (Role::Host { streams }, NetEvent::Received(id, Message::Join { name })) => {
+                    if let Some(stream) = streams.get_mut(&id) {
+                        if !send_or_close(stream, &Message::Welcome { id }) {
+                            streams.remove(&id);
+                        }
+                    }
+                    self.players.push(Player {
+                        id,
+                        name,
+                        progress: Progress::default(),
+                    });
+                    changed = true;
+                }
+                (Role::Host { .. }, NetEvent::Received(id, Message::Progress { progress })) => {
+                    if let Some(player) = self.players.iter_mut().find(|p| p.id == id) {
+                        player.progress = progress;
+                        changed = true;
+                    }
+                }
+                (Role::Host { streams }, NetEvent::Disconnected(id)) => {
+                    streams.remove(&id);
+                    self.players.retain(|p| p.id != id);
+                    changed = true;
+                }
+                (Role::Player { .. }, NetEvent::Received(_, Message::Welcome { id })) => {
+                    self.you = id;
+                }
+                (Role::Player { .. }, NetEvent::Received(_, Message::Board { players })) => {
+                    self.players = players;
+                }
+                (
+                    Role::Player { .. },
+                    NetEvent::Received(
+                        _,
+                        Message::Start {
+                            words, settings, ..
+                        },
+                    ),
+                ) => {
+                    let test = settings.test(words);
+                    // a test without words can't be typed or drawn
+                    if test.words.is_empty() {
+                        return Err(Error::Network {
+                            addr: self.addr.clone(),
+                            source: io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "the host started a race with no words",
+                            ),
+                        });
+                    }
+                    started = Some(test);
+                }
+                (Role::Player { .. }, NetEvent::Disconnected(_)) => {
+                    return Err(Error::Network {
+                        addr: self.addr.clone(),
+                        source: io::ErrorKind::ConnectionAborted.into(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        if changed {
+            self.broadcast_board();
+        }
+        Ok(started)
+    }
+
+    /// Share the local player's progress with the others
+    pub fn update(&mut self, progress: Progress) {
+        if let Some(player) = self.players.iter_mut().find(|p| p.id == self.you) {
+            player.progress = progress;
+        }
+        match &mut self.role {
+            Role::Host { .. } => self.broadcast_board(),
+            Role::Player { stream } => {
+                // a lost connection is noticed by the reader
+                let _ = send(stream, &Message::Progress { progress });
+            }
+        }
+    }
+
+    /// Start a race of the given test for every player, which only the host can do
+    pub fn start(&mut self, test: &Test, seed: u64) {
+        for player in &mut self.players {
+            player.progress = Progress::default();
+        }
+        self.broadcast(&Message::start(test, seed));
+        self.broadcast_board();
+    }
+
+    fn broadcast_board(&mut self) {
+        let board = Message::Board {
+            players: self.players.clone(),
+        };
+        self.broadcast(&board);
+    }
+
+    /// Send a message to every player if this is the host, dropping players who can't be reached
+    /// in time
+    fn broadcast(&mut self, message: &Message) {
+        if let Role::Host { streams } = &mut self.role {
+            streams.retain(|_, stream| send_or_close(stream, message));
+        }
+    }
+}
+
+impl Drop for Peer {
+    fn drop(&mut self) {
+        // the readers hold their own handles to the connections, so close them explicitly
+        let streams: Vec<&TcpStream> = match &self.role {
+            Role::Host { streams } => streams.values().collect(),
+            Role::Player { stream } => vec![stream],
+        };
+        for stream in streams {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Name shown to other players, which is the user's login name unless one is given
+pub fn player_name(name: Option<String>) -> String {
+    name.or_else(|| env::var("USER").ok())
+        .or_else(|| env::var("USERNAME").ok())
+        .unwrap_or_else(|| String::from("player"))
+}
+
+enum Stage {
+    /// Waiting for the host to start a race
+    Lobby,
+    Racing(Box<Test>),
+    /// Finished the race, while others may still be racing
+    Finished,
+}
+
+/// Take part in races until the player quits
+pub fn run(opt: &Opt, config: &Config, mut peer: Peer) -> error::Result<()> {
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+    let _guard = TerminalGuard::enter()?;
+    terminal.clear()?;
+
+    let mut seed = opt.seed.unwrap_or_else(|| thread_rng().gen());
+    let mut stage = Stage::Lobby;
+    let mut last_update = Instant::now();
+    loop {
+        if let Some(test) = peer.poll()? {
+            stage = Stage::Racing(Box::new(opt.emulate(test)));
+        }
+        if let Stage::Racing(ref test) = stage {
+            if test
+                .deadline()
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                stage = finish(&mut peer, test);
+            } else if test.start.is_some() && last_update.elapsed() >= PROGRESS_TICK {
+                peer.update(Progress::of(test, None));
+                last_update = Instant::now();
+            }
+        }
+
+        render(&mut terminal, config, &peer, &stage)?;
+        if !event::poll(NET_TICK)? {
+            continue;
+        }
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let action = match stage {
+            Stage::Racing(ref test) => config.keys.action(Screen::of(test), key),
+            // keys don't type outside of races, like on the results screen
+            Stage::Lobby | Stage::Finished => config.keys.action(Screen::Results, key),
+        };
+        if action == Some(Action::Quit) {
+            break;
+        }
+
+        match stage {
+            Stage::Racing(ref mut test) => {
+                match action {
+                    Some(Action::AbortToResults) => {
+                        stage = finish(&mut peer, test);
+                        continue;
+                    }
+                    Some(Action::DeleteWord) => test.delete_word(key),
+                    _ => test.handle_key(key),
+                }
+                if test.complete {
+                    stage = finish(&mut peer, test);
+                } else {
+                    peer.update(Progress::of(test, None));
+                    last_update = Instant::now();
+                }
+            }
+            Stage::Lobby | Stage::Finished => match key.code {
+                KeyCode::Enter if peer.is_host() => {
+                    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                    let (mut test, _) = opt.gen_test(None, &mut rng)?;
+                    if let (Some(secs), true) = (opt.time, opt.generates_words()) {
+                        let needed = (secs.get() * TIMED_WORDS_PER_SECOND) as usize;
+                        let missing = needed.saturating_sub(test.words.len());
+                        test.extend(opt.gen_words(missing, &mut rng)?);
+                    }
+
+                    peer.start(&test, seed);
+                    stage = Stage::Racing(Box::new(test));
+                    seed = derive_seed(seed, RESTART_STREAM);
+                }
+                _ => {}
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Controls of the host between races, who starts them with enter
+fn start_prompt(config: &Config, start: &str) -> String {
+    match config.keys.name(Action::Quit) {
+        Some(quit) => format!("Press enter to {} or {} to quit", start, quit),
+        None => format!("Press enter to {}", start),
+    }
+}
+
+/// Share the score of the local player's finished test
+fn finish(peer: &mut Peer, test: &Test) -> Stage {
+    peer.update(Progress::of(test, Some(&Results::from(test))));
+    Stage::Finished
+}
+
+fn render<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    config: &Config,
+    peer: &Peer,
+    stage: &Stage,
+) -> io::Result<()> {
+    terminal.draw(|f| match stage {
+        Stage::Lobby => {
+            let board = ui::RaceBoard {
+                players: &peer.players,
+                you: peer.you,
+                length: 1,
+            };
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(board.height()), Constraint::Min(1)])
+                .split(f.size());
+            f.render_widget(config.theme.apply_to(board), chunks[0]);
+
+            let prompt = if peer.is_host() {
+                format!(
+                    "Waiting for players to join at {}. {}",
+                    peer.addr,
+                    start_prompt(config, "start the race")
+                )
+            } else {
+                format!(
+                    "Waiting for the host to start the race. {}",
+                    config.keys.prompt(&[(Action::Quit, "to quit")])
+                )
+            };
+            f.render_widget(
+                Paragraph::new(prompt).style(config.theme.results_restart_prompt),
+                chunks[1],
+            );
+        }
+        Stage::Racing(test) => {
+            let length = match test.time_limit {
+                // there's more text than anyone can type, so bars are relative to the leader
+                Some(_) => peer.players.iter().map(|p| p.progress.chars).max(),
+                None => Some(ghost::prompt_len(&test.words)),
+            };
+            let board = ui::RaceBoard {
+                players: &peer.players,
+                you: peer.you,
+                length: length.unwrap_or_default(),
+            };
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(board.height())])
+                .split(f.size());
+            f.render_widget(config.theme.apply_to(board), chunks[1]);
+            draw_test(f, chunks[0], test, config);
+        }
+        Stage::Finished => {
+            let prompt = if peer.is_host() {
+                start_prompt(config, "race again")
+            } else {
+                format!(
+                    "Waiting for the host to start another race. {}",
+                    config.keys.prompt(&[(Action::Quit, "to quit")])
+                )
+            };
+            f.render_widget(
+                config.theme.apply_to(ui::Scoreboard {
+                    players: &peer.players,
+                    you: peer.you,
+                    prompt: &prompt,
+                }),
+                f.size(),
+            );
+        }
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    /// Poll a peer until the condition holds, returning the last race it was started on
+    fn poll_until(peer: &mut Peer, done: impl Fn(&Peer, &Option<Test>) -> bool) -> Option<Test> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut started = None;
+        loop {
+            if let Some(test) = peer.poll().unwrap() {
+                started = Some(test);
+            }
+            if done(peer, &started) {
+                return started;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for peer");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn races_over_loopback() {
+        let mut host = Peer::host(0, String::from("host")).unwrap();
+        let port = host.addr.rsplit_once(':').unwrap().1.to_owned();
+        let mut player = Peer::join(&format!("127.0.0.1:{}", port), String::from("guest")).unwrap();
+
+        poll_until(&mut host, |host, _| host.players.len() == 2);
+        poll_until(&mut player, |player, _| {
+            player.you == 1 && player.players.len() == 2
+        });
+
+        let test = Test::new(vec!["ab".into(), "cd".into()], true, false, true, None);
+        host.start(&test, 42);
+        let mut raced = poll_until(&mut player, |_, started| started.is_some()).unwrap();
+        assert_eq!(raced.words[1].text, "cd");
+
+        for c in "ab cd".chars() {
+            raced.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        assert!(raced.complete);
+        player.update(Progress::of(&raced, Some(&Results::from(&raced))));
+
+        poll_until(&mut host, |host, _| {
+            host.players[1].progress.chars == 5 && host.players[1].progress.score.is_some()
+        });
+        poll_until(&mut player, |player, _| {
+            player.players[1].progress.score.is_some()
+        });
+
+        drop(host);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while player.poll().is_ok() {
+            assert!(Instant::now() < deadline, "host disconnect wasn't noticed");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn drops_players_sending_overlong_lines() {
+        let mut host = Peer::host(0, String::from("host")).unwrap();
+        let port = host.addr.rsplit_once(':').unwrap().1.to_owned();
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+        send(
+            &mut stream,
+            &Message::Join {
+                name: "guest".into(),
+            },
+        )
+        .unwrap();
+        poll_until(&mut host, |host, _| host.players.len() == 2);
+
+        // the host stops reading once the line is too long, so the write may fail
+        let line = vec![b'a'; MAX_LINE_LEN as usize + 1];
+        let _ = stream.write_all(&line);
+        poll_until(&mut host, |host, _| host.players.len() == 1);
+    }
+
+    #[test]
+    fn rejects_races_without_words() {
+        let mut host = Peer::host(0, String::from("host")).unwrap();
+        let port = host.addr.rsplit_once(':').unwrap().1.to_owned();
+        let mut player = Peer::join(&format!("127.0.0.1:{}", port), String::from("guest")).unwrap();
+        poll_until(&mut host, |host, _| host.players.len() == 2);
+
+        let mut test = Test::new(vec!["ab".into()], true, false, true, None);
+        test.words.clear();
+        host.start(&test, 42);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let err = loop {
+            match player.poll() {
+                Ok(started) => assert!(started.is_none(), "started a race with no words"),
+                Err(err) => break err,
+            }
+            assert!(Instant::now() < deadline, "empty race wasn't rejected");
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert!(matches!(
+            err,
+            Error::Network { source, .. } if source.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+}
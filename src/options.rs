@@ -0,0 +1,209 @@
+use crate::layers::{Layers, Source};
+use crate::{export, parse_positive, parse_rate, quote, Opt};
+
+use clap::{parser::ValueSource, ArgMatches};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{num, path::PathBuf};
+use ttyper::{config::Config, test::ErrorPolicy};
+
+/// Declare the options which can be set in config files, named as they are on the command line
+macro_rules! options {
+    ($($(#[$attr:meta])* $field:ident: $type:ty,)*) => {
+        /// Options which can be set in config files as well as on the command line
+        #[derive(Debug, Default, Serialize, Deserialize)]
+        #[serde(default)]
+        pub struct Options {
+            $($(#[$attr])* pub $field: Option<$type>,)*
+        }
+
+        impl Options {
+            /// Names of the options, which are also their keys in config files
+            pub const KEYS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            /// Set the options which weren't given as arguments or environment variables
+            pub fn apply(self, opt: &mut Opt, matches: &ArgMatches) {
+                $(if let Some(value) = self.$field {
+                    if !from_args(matches, stringify!($field)) {
+                        opt.$field = value.into();
+                    }
+                })*
+            }
+
+            /// Effective options, after they've been applied
+            pub fn of(opt: &Opt) -> Self {
+                Self {
+                    $($field: opt.$field.clone().into(),)*
+                }
+            }
+        }
+    };
+}
+
+options! {
+    words: num::NonZeroUsize,
+    time: num::NonZeroU64,
+    language: String,
+    language_file: PathBuf,
+    quote: quote::Selector,
+    code: bool,
+    tab_width: num::NonZeroUsize,
+    require_indent: bool,
+    punctuation: bool,
+    #[serde(deserialize_with = "rate")]
+    punctuation_rate: f64,
+    numbers: bool,
+    #[serde(deserialize_with = "rate")]
+    numbers_rate: f64,
+    seed: u64,
+    #[serde(deserialize_with = "positive")]
+    pace: f64,
+    no_backtrack: bool,
+    sudden_death: bool,
+    no_backspace: bool,
+    errors: ErrorPolicy,
+    output: PathBuf,
+    format: export::Format,
+    no_history: bool,
+    record: PathBuf,
+    layout: String,
+}
+
+/// Whether an option was given as an argument or environment variable, which override config files
+fn from_args(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+/// Where the effective value of an option came from, given the config key which also sets it
+fn source(matches: &ArgMatches, layers: &Layers, id: &str, keys: &[&str]) -> Source {
+    match matches.value_source(id) {
+        Some(ValueSource::CommandLine) => Source::CommandLine,
+        Some(ValueSource::EnvVariable) => Source::Env(format!("TTYPER_{}", id.to_uppercase())),
+        // the language falls back to the default language in the config
+        _ if id == "language" => match layers.source(keys) {
+            Source::Default => layers.source(&["default_language"]),
+            source => source,
+        },
+        _ => layers.source(keys),
+    }
+}
+
+/// Print the effective options and config, along with where each value came from
+pub fn print_effective(opt: &Opt, matches: &ArgMatches, layers: &Layers, config: &Config) {
+    let print = |key: &str, value: &toml::Value, source: Source| {
+        eprintln!("{} = {}  # {}", key, value, source);
+    };
+
+    // unset options are skipped when serializing
+    if let Ok(options) = toml::Table::try_from(Options::of(opt)) {
+        for (key, value) in &options {
+            print(key, value, source(matches, layers, key, &[key]));
+        }
+    }
+
+    if let Ok(config) = toml::Table::try_from(config) {
+        let mut entries = Vec::new();
+        flatten(&config, &mut Vec::new(), &mut entries);
+        for (keys, value) in entries {
+            let source = match keys[..] {
+                ["theme", "name"] => source(matches, layers, "theme", &keys),
+                _ => layers.source(&keys),
+            };
+            print(&keys.join("."), value, source);
+        }
+    }
+}
+
+/// Effective options and config as the text of a config file, including defaults
+pub fn dump(opt: &Opt, config: &Config) -> String {
+    // everything here was deserialized from a config file or has a default which serializes
+    let mut table = toml::Table::try_from(Options::of(opt)).expect("options should serialize");
+    table.extend(toml::Table::try_from(config).expect("config should serialize"));
+    toml::to_string(&table).expect("config should serialize")
+}
+
+/// Values of nested tables, along with the path of keys to each
+fn flatten<'a>(
+    table: &'a toml::Table,
+    keys: &mut Vec<&'a str>,
+    entries: &mut Vec<(Vec<&'a str>, &'a toml::Value)>,
+) {
+    for (key, value) in table {
+        keys.push(key);
+        match value {
+            toml::Value::Table(table) => flatten(table, keys, entries),
+            value => entries.push((keys.clone(), value)),
+        }
+        keys.pop();
+    }
+}
+
+/// Deserialize a rate with the same rules as its command line argument
+fn rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    let rate = f64::deserialize(deserializer)?;
+    parse_rate(&rate.to_string())
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+/// Deserialize a positive number with the same rules as its command line argument
+fn positive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    let value = f64::deserialize(deserializer)?;
+    parse_positive(&value.to_string())
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    #[test]
+    fn arguments_override_config() {
+        let matches = Opt::command()
+            .try_get_matches_from(["ttyper", "--words", "10", "--no-backspace"])
+            .unwrap();
+        let mut opt = Opt::from_arg_matches(&matches).unwrap();
+
+        let options: Options = toml::from_str(
+            "words = 25\ntime = 30\nsudden_death = true\nno_backspace = false\nformat = \"csv\"\n",
+        )
+        .unwrap();
+        options.apply(&mut opt, &matches);
+
+        assert_eq!(opt.words.get(), 10);
+        assert!(opt.no_backspace);
+        assert_eq!(opt.time.map(|t| t.get()), Some(30));
+        assert!(opt.sudden_death);
+        assert_eq!(opt.format, Some(export::Format::Csv));
+        assert_eq!(opt.tab_width.get(), 4);
+    }
+
+    #[test]
+    fn arguments_turn_off_config_flags() {
+        let matches = Opt::command()
+            .try_get_matches_from(["ttyper", "--sudden-death=false", "--code", "main.go"])
+            .unwrap();
+        let mut opt = Opt::from_arg_matches(&matches).unwrap();
+
+        let options: Options = toml::from_str("sudden_death = true\nno_history = true\n").unwrap();
+        options.apply(&mut opt, &matches);
+
+        assert!(!opt.sudden_death);
+        assert!(opt.no_history);
+        assert!(opt.code);
+        assert_eq!(opt.contents, Some(PathBuf::from("main.go")));
+    }
+
+    #[test]
+    fn validates_rates() {
+        assert!(toml::from_str::<Options>("numbers_rate = 0.5").is_ok());
+        assert!(toml::from_str::<Options>("numbers_rate = 1.5").is_err());
+        assert!(toml::from_str::<Options>("pace = 0").is_err());
+        assert!(toml::from_str::<Options>("quote = \"short\"").is_ok());
+        assert!(toml::from_str::<Options>("quote = \"shortest\"").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use ttyper::test::results::Results;
+
+use crossterm::event::KeyCode;
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use std::collections::{HashMap, HashSet};
+
+// Weight of a word with no weak characters, relative to each unit of weakness
+const BASE_WEIGHT: f64 = 0.1;
+
+// How much more an error counts than being twice as slow as usual
+const ERROR_WEIGHT: f64 = 2.0;
+
+/// How much practice characters and bigrams need, where 0 means none
+#[derive(Debug, Default)]
+pub struct Weaknesses {
+    pub keys: HashMap<char, f64>,
+    pub bigrams: HashMap<(char, char), f64>,
+}
+
+impl Weaknesses {
+    /// Score weaknesses by error rate and by latency relative to the median
+    pub fn from_results(results: &Results) -> Self {
+        let mut keys: HashMap<char, f64> = HashMap::new();
+
+        for (key, accuracy) in &results.accuracy.per_key {
+            if let KeyCode::Char(c) = key.code {
+                *keys.entry(c).or_default() += (1.0 - f64::from(*accuracy)) * ERROR_WEIGHT;
+            }
+        }
+
+        let latencies: Vec<(char, f64)> = results
+            .timing
+            .per_key
+            .iter()
+            .filter_map(|(key, latency)| match key.code {
+                KeyCode::Char(c) => Some((c, *latency)),
+                _ => None,
+            })
+            .collect();
+        let key_median = median(latencies.iter().map(|(_, l)| *l));
+        for (c, latency) in latencies {
+            *keys.entry(c).or_default() += slowness(latency, key_median);
+        }
+
+        let bigram_median = median(results.bigrams.values().map(|s| s.latency));
+        let bigrams = results
+            .bigrams
+            .iter()
+            .map(|(bigram, stats)| {
+                let weakness = (1.0 - f64::from(stats.accuracy)) * ERROR_WEIGHT
+                    + slowness(stats.latency, bigram_median);
+                (*bigram, weakness)
+            })
+            .collect();
+
+        // spaces are typed between every word, so they can't be drilled by choosing words
+        keys.remove(&' ');
+
+        Self { keys, bigrams }
+    }
+
+    /// Total weakness of the characters and bigrams in a word
+    pub fn score(&self, word: &str) -> f64 {
+        let chars: Vec<char> = word.chars().collect();
+
+        let key_score: f64 = chars
+            .iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter_map(|c| self.keys.get(c))
+            .sum();
+        let bigram_score: f64 = chars
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter_map(|bigram| self.bigrams.get(&bigram))
+            .sum();
+
+        key_score + bigram_score
+    }
+}
+
+/// How much slower than the median a latency is, e.g. 0.5 for 1.5 times the median
+fn slowness(latency: f64, median: Option<f64>) -> f64 {
+    match median {
+        Some(median) if median > 0.0 => (latency / median - 1.0).max(0.0),
+        _ => 0.0,
+    }
+}
+
+fn median(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let mut values: Vec<f64> = values.filter(|v| v.is_finite()).collect();
+    values.sort_unstable_by(f64::total_cmp);
+    values.get(values.len() / 2).copied()
+}
+
+/// Choose words from a language, favoring those which train the given weaknesses
+pub fn drill<R: Rng + ?Sized>(
+    weaknesses: &Weaknesses,
+    language: &[String],
+    count: usize,
+    rng: &mut R,
+) -> Vec<String> {
+    let weights = language
+        .iter()
+        .map(|word| BASE_WEIGHT + weaknesses.score(word));
+
+    match WeightedIndex::new(weights) {
+        Ok(index) => (0..count)
+            .map(|_| language[index.sample(rng)].clone())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn favors_weak_words() {
+        let weaknesses = Weaknesses {
+            keys: HashMap::from([('x', 1.0)]),
+            bigrams: HashMap::from([(('q', 'u'), 1.0)]),
+        };
+        assert_eq!(weaknesses.score("box"), 1.0);
+        assert_eq!(weaknesses.score("quiz"), 1.0);
+        assert_eq!(weaknesses.score("the"), 0.0);
+
+        let language: Vec<String> = ["box", "the", "and", "quiz"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let words = drill(&weaknesses, &language, 1000, &mut StdRng::seed_from_u64(0));
+        let count = |word| words.iter().filter(|w| *w == word).count();
+
+        assert_eq!(words.len(), 1000);
+        assert!(count("box") > 3 * count("the"));
+        assert!(count("quiz") > 3 * count("and"));
+    }
+
+    #[test]
+    fn scores_slowness() {
+        assert_eq!(slowness(0.75, Some(0.5)), 0.5);
+        assert_eq!(slowness(0.25, Some(0.5)), 0.0);
+        assert_eq!(slowness(0.1, None), 0.0);
+        assert_eq!(median([3.0, 1.0, 2.0].into_iter()), Some(2.0));
+    }
+}
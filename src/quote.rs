@@ -0,0 +1,158 @@
+use rand::{seq::SliceRandom, Rng};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+// Quotes shorter than this many characters are short
+const SHORT_MAX: usize = 100;
+// Quotes shorter than this many characters are medium
+const MEDIUM_MAX: usize = 250;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Length {
+    Short,
+    Medium,
+    Long,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Quote {
+    pub id: u32,
+    pub source: String,
+    pub text: String,
+}
+
+impl Quote {
+    pub fn length(&self) -> Length {
+        match self.text.chars().count() {
+            n if n < SHORT_MAX => Length::Short,
+            n if n < MEDIUM_MAX => Length::Medium,
+            _ => Length::Long,
+        }
+    }
+
+    pub fn words(&self) -> Vec<String> {
+        self.text.split_whitespace().map(String::from).collect()
+    }
+
+    pub fn attribution(&self) -> String {
+        format!("{} (#{})", self.source, self.id)
+    }
+}
+
+#[derive(Deserialize)]
+struct QuoteFile {
+    quote: Vec<Quote>,
+}
+
+/// Parse a quote file, which is a TOML document with a `[[quote]]` table per quote
+pub fn parse(text: &str) -> Result<Vec<Quote>, toml::de::Error> {
+    toml::from_str::<QuoteFile>(text).map(|file| file.quote)
+}
+
+/// Which quotes to choose from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selector {
+    Any,
+    Length(Length),
+    Id(u32),
+}
+
+impl Selector {
+    pub fn choose<'q, R: Rng + ?Sized>(
+        &self,
+        quotes: &'q [Quote],
+        rng: &mut R,
+    ) -> Option<&'q Quote> {
+        let candidates: Vec<&Quote> = quotes
+            .iter()
+            .filter(|quote| match *self {
+                Selector::Any => true,
+                Selector::Length(length) => quote.length() == length,
+                Selector::Id(id) => quote.id == id,
+            })
+            .collect();
+
+        candidates.choose(rng).copied()
+    }
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(Selector::Any),
+            "short" => Ok(Selector::Length(Length::Short)),
+            "medium" => Ok(Selector::Length(Length::Medium)),
+            "long" => Ok(Selector::Length(Length::Long)),
+            _ => s.parse().map(Selector::Id).map_err(|_| {
+                format!(
+                    "invalid quote `{}`, expected short, medium, long, any or a quote id",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Any => write!(f, "any"),
+            Selector::Length(Length::Short) => write!(f, "short"),
+            Selector::Length(Length::Medium) => write!(f, "medium"),
+            Selector::Length(Length::Long) => write!(f, "long"),
+            Selector::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl Serialize for Selector {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Selector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::collections::HashSet;
+
+    #[test]
+    fn parses_builtin_quotes() {
+        let quotes = parse(include_str!("../resources/runtime/quote/english"))
+            .expect("failed to parse builtin quotes");
+
+        let ids: HashSet<u32> = quotes.iter().map(|q| q.id).collect();
+        assert_eq!(ids.len(), quotes.len(), "quote ids must be unique");
+
+        for length in [Length::Short, Length::Medium, Length::Long] {
+            assert!(quotes.iter().any(|q| q.length() == length));
+        }
+    }
+
+    #[test]
+    fn chooses_quotes() {
+        let quotes = parse(include_str!("../resources/runtime/quote/english")).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let selector: Selector = "long".parse().unwrap();
+        let quote = selector.choose(&quotes, &mut rng).unwrap();
+        assert_eq!(quote.length(), Length::Long);
+
+        let selector: Selector = "3".parse().unwrap();
+        assert_eq!(selector.choose(&quotes, &mut rng).unwrap().id, 3);
+
+        assert!("tiny".parse::<Selector>().is_err());
+        assert!(Selector::Id(0).choose(&quotes, &mut rng).is_none());
+    }
+}
@@ -0,0 +1,179 @@
+use crate::ghost;
+use crate::recording::{prompt_words, Settings};
+use crate::test::{
+    results::{live_wpm, Results},
+    Test,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A message between the host of a race and a player, sent as a line of JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// Sent by a player when connecting to the host
+    Join {
+        /// Name shown to the other players
+        name: String,
+    },
+    /// Sent by the host to a player who joined, with the id identifying them on the board
+    Welcome {
+        /// Id of the player
+        id: usize,
+    },
+    /// Sent by the host to start a race, which every player takes on the same test
+    Start {
+        /// Words of the prompt
+        words: Vec<String>,
+        /// Settings of the test
+        settings: Settings,
+        /// Seed the words were generated from
+        seed: u64,
+    },
+    /// Sent by a player whenever they make progress
+    Progress {
+        /// The player's progress
+        progress: Progress,
+    },
+    /// Sent by the host whenever a player joins, leaves or makes progress
+    Board {
+        /// Every player in the race, including the host
+        players: Vec<Player>,
+    },
+}
+
+impl Message {
+    /// Message starting a race on the given test
+    pub fn start(test: &Test, seed: u64) -> Self {
+        Message::Start {
+            words: prompt_words(test),
+            settings: Settings::from(test),
+            seed,
+        }
+    }
+}
+
+/// A participant in a race
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    /// Id assigned by the host, which is 0 for the host itself
+    pub id: usize,
+    /// Name shown to the other players
+    pub name: String,
+    /// How far the player is through the current race
+    pub progress: Progress,
+}
+
+/// How far a player is through a race
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    /// Characters of the prompt passed so far
+    pub chars: usize,
+    /// Speed so far, counting every keypress
+    pub wpm: f64,
+    /// Final score, once the player has finished
+    pub score: Option<Score>,
+}
+
+impl Progress {
+    /// Progress through a test being raced, which is scored once it's finished
+    pub fn of(test: &Test, results: Option<&Results>) -> Self {
+        Self {
+            chars: ghost::progress(test),
+            wpm: live_wpm(test),
+            score: results.map(Score::from),
+        }
+    }
+}
+
+/// Final score of a player, shared on the scoreboard
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    /// Words per minute, scaled by accuracy
+    pub wpm: f64,
+    /// Words per minute, counting every keypress
+    pub raw_wpm: f64,
+    /// Fraction of keypresses which were correct
+    pub accuracy: f64,
+}
+
+impl From<&Results> for Score {
+    fn from(results: &Results) -> Self {
+        // tests finished without typing anything have no speed or accuracy
+        let finite = |x: f64| if x.is_finite() { x } else { 0.0 };
+        Self {
+            wpm: finite(results.adjusted_wpm()),
+            raw_wpm: finite(results.raw_wpm()),
+            accuracy: finite(f64::from(results.accuracy.overall)),
+        }
+    }
+}
+
+/// Order of players on the scoreboard: finished players by score, then the rest by progress
+pub fn standings(players: &[Player]) -> Vec<&Player> {
+    let mut standings: Vec<&Player> = players.iter().collect();
+    standings.sort_by(|a, b| match (a.progress.score, b.progress.score) {
+        (Some(a), Some(b)) => b.wpm.total_cmp(&a.wpm),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => b.progress.chars.cmp(&a.progress.chars),
+    });
+    standings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_the_same_test() {
+        let test = Test::new(vec!["ab".into(), "  cd".into()], true, false, true, None).with_code(
+            crate::test::CodeOptions {
+                tab_width: 4,
+                require_indent: false,
+            },
+        );
+        let line = serde_json::to_string(&Message::start(&test, 7)).unwrap();
+        assert!(!line.contains('\n'));
+
+        match serde_json::from_str(&line).unwrap() {
+            Message::Start {
+                words,
+                settings,
+                seed,
+            } => {
+                let raced = settings.test(words);
+                assert_eq!(seed, 7);
+                assert_eq!(raced.words[1].text, "cd");
+                assert_eq!(raced.words[1].indent, 2);
+                assert_eq!(raced.code, test.code);
+            }
+            message => panic!("expected a start message, got {:?}", message),
+        }
+    }
+
+    #[test]
+    fn ranks_finished_players_first() {
+        let player = |id, chars, wpm: Option<f64>| Player {
+            id,
+            name: id.to_string(),
+            progress: Progress {
+                chars,
+                wpm: 0.0,
+                score: wpm.map(|wpm| Score {
+                    wpm,
+                    raw_wpm: wpm,
+                    accuracy: 1.0,
+                }),
+            },
+        };
+        let players = [
+            player(0, 3, None),
+            player(1, 10, Some(50.0)),
+            player(2, 8, None),
+            player(3, 10, Some(70.0)),
+        ];
+        let ids: Vec<usize> = standings(&players).iter().map(|p| p.id).collect();
+        assert_eq!(ids, [3, 1, 2, 0]);
+    }
+}
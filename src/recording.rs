@@ -0,0 +1,269 @@
+use crate::keys::{Action, KeyBindings, Screen};
+use crate::test::{CodeOptions, ErrorPolicy, Test};
+
+use crossterm::event::{KeyEvent, KeyEventKind};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+/// Version of the recording format, increased when it changes incompatibly
+pub const FORMAT_VERSION: u32 = 2;
+
+/// A test's prompt, settings and every key pressed while taking it, which can be played back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    /// Version of the recording format
+    pub version: u32,
+    /// Words of the prompt, with the indentation of code lines
+    pub words: Vec<String>,
+    /// Settings the test was taken with
+    pub settings: Settings,
+    /// Keys in the order they were pressed
+    pub keys: Vec<RecordedKey>,
+}
+
+/// Settings of a recorded test
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// Whether mistyped words can be returned to after moving past them
+    pub backtracking: bool,
+    /// Whether the test restarts on the first mistake
+    pub sudden_death: bool,
+    /// Whether typed characters can be deleted
+    pub backspace: bool,
+    /// How mistakes were handled
+    #[serde(default)]
+    pub errors: ErrorPolicy,
+    /// Time limit in seconds, if the test was timed
+    pub time_limit: Option<f64>,
+    /// Settings for source code tests
+    pub code: Option<CodeOptions>,
+}
+
+impl Settings {
+    /// A test of the given words with these settings
+    pub fn test(&self, words: Vec<String>) -> Test {
+        let test = Test::new(
+            words,
+            self.backtracking,
+            self.sudden_death,
+            self.backspace,
+            self.time_limit
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok()),
+        )
+        .with_error_policy(self.errors);
+
+        match self.code {
+            Some(code) => test.with_code(code),
+            None => test,
+        }
+    }
+}
+
+impl From<&Test> for Settings {
+    fn from(test: &Test) -> Self {
+        Self {
+            backtracking: test.backtracking_enabled,
+            sudden_death: test.sudden_death_enabled,
+            backspace: test.backspace_enabled,
+            errors: test.error_policy,
+            time_limit: test.time_limit.map(|limit| limit.as_secs_f64()),
+            code: test.code,
+        }
+    }
+}
+
+/// Words of a test's prompt as they were passed to it, restoring the indentation of code lines
+pub fn prompt_words(test: &Test) -> Vec<String> {
+    test.words
+        .iter()
+        .map(|word| format!("{}{}", " ".repeat(word.indent), word.text))
+        .collect()
+}
+
+/// A key pressed during a recorded test
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecordedKey {
+    /// Seconds since the first key of the test was pressed
+    pub time: f64,
+    /// The key that was pressed
+    pub key: KeyEvent,
+    /// Action the key was bound to, if it was handled as one rather than typed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<Action>,
+}
+
+impl RecordedKey {
+    /// Pass the key to a test as it was handled when recorded, pressed at the given time
+    pub fn replay(&self, test: &mut Test, time: Instant) {
+        match self.action {
+            Some(Action::DeleteWord) => test.delete_word_at(self.key, time),
+            _ => test.handle_key_at(self.key, time),
+        }
+    }
+}
+
+impl Recording {
+    /// A fresh copy of the recorded test, before any keys were pressed
+    pub fn test(&self) -> Test {
+        self.settings.test(self.words.clone())
+    }
+
+    /// Time from the first key to the last
+    pub fn duration(&self) -> Duration {
+        self.keys
+            .last()
+            .and_then(|key| Duration::try_from_secs_f64(key.time).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write the recording as JSON
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer(writer, self).map_err(io::Error::from)
+    }
+
+    /// Read a recording written by [`Recording::write`]
+    pub fn read<R: Read>(reader: R) -> serde_json::Result<Self> {
+        let mut recording: Self = serde_json::from_reader(reader)?;
+        if recording.version < 2 {
+            // actions weren't recorded before keys could be rebound, so they had their defaults
+            let defaults = KeyBindings::default();
+            for key in &mut recording.keys {
+                key.action = defaults
+                    .action(Screen::Test, key.key)
+                    .filter(|&action| action == Action::DeleteWord);
+            }
+        }
+        if recording.test().words.is_empty() {
+            return Err(serde::de::Error::custom("recording has no words to type"));
+        }
+        Ok(recording)
+    }
+}
+
+/// Collects the keys pressed during a test, to be turned into a [`Recording`] once it's finished
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    start: Option<Instant>,
+    keys: Vec<RecordedKey>,
+}
+
+impl Recorder {
+    /// Record a key passed to [`Test::handle_key`], or the action it was bound to
+    pub fn record(&mut self, key: KeyEvent, action: Option<Action>) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+
+        let start = *self.start.get_or_insert_with(Instant::now);
+        self.keys.push(RecordedKey {
+            time: start.elapsed().as_secs_f64(),
+            key,
+            action,
+        });
+    }
+
+    /// Recording of the keys pressed while taking the given test
+    pub fn finish(&self, test: &Test) -> Recording {
+        Recording {
+            version: FORMAT_VERSION,
+            words: prompt_words(test),
+            settings: Settings::from(test),
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn replays_recorded_keys() {
+        let mut test = Test::new(vec!["ab".into(), "cd".into()], true, false, true, None);
+        let mut recorder = Recorder::default();
+        for code in [
+            KeyCode::Char('a'),
+            KeyCode::Char('b'),
+            KeyCode::Char(' '),
+            KeyCode::Backspace,
+            KeyCode::Char(' '),
+            KeyCode::Char('c'),
+            KeyCode::Char('x'),
+            KeyCode::Char('y'),
+        ] {
+            let key = KeyEvent::new(code, KeyModifiers::NONE);
+            recorder.record(key, None);
+            test.handle_key(key);
+        }
+        let key = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        recorder.record(key, Some(Action::DeleteWord));
+        test.delete_word(key);
+        for c in "cd".chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            recorder.record(key, None);
+            test.handle_key(key);
+        }
+        assert!(test.complete);
+
+        let mut json = Vec::new();
+        recorder.finish(&test).write(&mut json).unwrap();
+        let recording = Recording::read(json.as_slice()).unwrap();
+        assert_eq!(recording, recorder.finish(&test));
+
+        let mut replayed = recording.test();
+        let start = Instant::now();
+        for key in &recording.keys {
+            key.replay(&mut replayed, start + Duration::from_secs_f64(key.time));
+        }
+        assert!(replayed.complete);
+        for (word, original) in replayed.words.iter().zip(&test.words) {
+            assert_eq!(word.progress, original.progress);
+            assert_eq!(word.events.len(), original.events.len());
+        }
+    }
+
+    #[test]
+    fn reads_actions_of_version_1_recordings() {
+        let test = Test::new(vec!["ab".into()], true, false, true, None);
+        let mut recorder = Recorder::default();
+        recorder.record(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE), None);
+        recorder.record(
+            KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+            None,
+        );
+        let mut recording = recorder.finish(&test);
+        recording.version = 1;
+
+        let mut json = Vec::new();
+        recording.write(&mut json).unwrap();
+        let actions: Vec<_> = Recording::read(json.as_slice())
+            .unwrap()
+            .keys
+            .iter()
+            .map(|key| key.action)
+            .collect();
+        assert_eq!(actions, [None, Some(Action::DeleteWord)]);
+    }
+
+    #[test]
+    fn rejects_recordings_without_words() {
+        let test = Test::new(vec!["ab".into()], true, false, true, None);
+        let mut recording = Recorder::default().finish(&test);
+        for words in [vec![], vec![String::from("  ")]] {
+            recording.words = words;
+            recording.settings.code = Some(CodeOptions {
+                tab_width: 4,
+                require_indent: false,
+            });
+
+            let mut json = Vec::new();
+            recording.write(&mut json).unwrap();
+            let err = Recording::read(json.as_slice()).unwrap_err();
+            assert!(err.to_string().contains("no words"));
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Snippet {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct SnippetFile {
+    snippet: Vec<Snippet>,
+}
+
+/// Parse a code snippet file, which is a TOML document with a `[[snippet]]` table per snippet
+pub fn parse(text: &str) -> Result<Vec<String>, toml::de::Error> {
+    toml::from_str::<SnippetFile>(text)
+        .map(|file| file.snippet.into_iter().map(|s| s.text).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_builtin_snippets() {
+        for text in [
+            include_str!("../resources/runtime/code/go"),
+            include_str!("../resources/runtime/code/python"),
+            include_str!("../resources/runtime/code/rust"),
+        ] {
+            let snippets = parse(text).expect("failed to parse builtin snippets");
+            assert!(!snippets.is_empty());
+            assert!(snippets.iter().all(|s| s.lines().count() > 1));
+        }
+    }
+}
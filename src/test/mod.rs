@@ -1,15 +1,28 @@
+/// Statistics computed from finished tests
 pub mod results;
 
+use crate::ghost::Ghost;
+use crate::layout::Layout;
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use serde::{Deserialize, Serialize};
 use std::fmt;
-use std::time::Instant;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
+/// A keypress made during a test
 pub struct TestEvent {
+    /// When the key was pressed
     pub time: Instant,
+    /// The key that was pressed
     pub key: KeyEvent,
+    /// Whether the key was correct, or `None` for keys like backspace which aren't scored
     pub correct: Option<bool>,
 }
 
+/// Whether an event makes the word it was typed in count as missed
 pub fn is_missed_word_event(event: &TestEvent) -> bool {
     event.correct != Some(true)
 }
@@ -23,19 +36,26 @@ impl fmt::Debug for TestEvent {
     }
 }
 
+/// A word of a test, along with what has been typed of it
 #[derive(Debug)]
 pub struct TestWord {
+    /// The word to type
     pub text: String,
+    /// What has been typed of the word so far
     pub progress: String,
+    /// Keypresses made while typing the word
     pub events: Vec<TestEvent>,
+    /// Width of indentation shown before the word without being typed
+    pub indent: usize,
 }
 
 impl From<String> for TestWord {
     fn from(string: String) -> Self {
         TestWord {
-            text: string,
+            text: string.nfc().collect(),
             progress: String::new(),
             events: Vec::new(),
+            indent: 0,
         }
     }
 }
@@ -46,22 +66,156 @@ impl From<&str> for TestWord {
     }
 }
 
+impl TestWord {
+    /// Grapheme of the text at the cursor, which is after as many graphemes as have been typed
+    pub fn next_grapheme(&self) -> Option<&str> {
+        self.text
+            .graphemes(true)
+            .nth(self.progress.graphemes(true).count())
+    }
+}
+
+/// Whether typed text is correct so far for the given text. Each typed grapheme has to match the
+/// text's, except that the last can be partway through being composed.
+pub fn is_typed_so_far(text: &str, typed: &str) -> bool {
+    let mut text = text.graphemes(true);
+    let mut typed = typed.graphemes(true).peekable();
+    while let Some(grapheme) = typed.next() {
+        match text.next() {
+            Some(expected) if expected == grapheme => {}
+            Some(expected) if typed.peek().is_none() && is_composing(expected, grapheme) => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Whether a typed grapheme is partway to the expected one: the start of it as it's written, like
+/// a Devanagari consonant before its vowel sign, a Hangul syllable before its final consonant, or
+/// a letter with only some of its combining marks. A bare letter isn't partway to a precomposed
+/// accented one, so it's wrong in its place.
+fn is_composing(expected: &str, typed: &str) -> bool {
+    // both are composed already, as the text and progress are normalized
+    if expected.starts_with(typed) {
+        return true;
+    }
+
+    let is_hangul = |c| matches!(c, '\u{1100}'..='\u{11ff}' | '\u{ac00}'..='\u{d7a3}');
+    let composing = typed.chars().all(is_hangul)
+        || typed
+            .nfd()
+            .last()
+            .is_some_and(unicode_normalization::char::is_combining_mark);
+    let mut expected = expected.nfd();
+    composing && typed.nfd().all(|c| expected.next() == Some(c))
+}
+
+/// How a test handles mistakes
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorPolicy {
+    /// Mistyped characters are typed like any other, and can be deleted
+    #[default]
+    Normal,
+    /// Mistyped characters are rejected, so the cursor only moves past correct ones. Rejected
+    /// keypresses are still recorded as incorrect.
+    StopOnLetter,
+    /// Words can't be submitted until they're typed correctly. Attempts to submit them early are
+    /// recorded as incorrect.
+    StopOnWord,
+    /// Nothing can be deleted, but backspace presses are still recorded as unscored attempts at
+    /// corrections, which make the word count as missed
+    Confidence,
+}
+
+impl ErrorPolicy {
+    /// Every policy, in the order they're listed
+    pub const ALL: [ErrorPolicy; 4] = [
+        ErrorPolicy::Normal,
+        ErrorPolicy::StopOnLetter,
+        ErrorPolicy::StopOnWord,
+        ErrorPolicy::Confidence,
+    ];
+
+    /// Name of the policy in arguments and config files
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorPolicy::Normal => "normal",
+            ErrorPolicy::StopOnLetter => "stop-on-letter",
+            ErrorPolicy::StopOnWord => "stop-on-word",
+            ErrorPolicy::Confidence => "confidence",
+        }
+    }
+}
+
+impl FromStr for ErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|policy| policy.name()).collect();
+                format!("expected one of {}", names.join(", "))
+            })
+    }
+}
+
+impl fmt::Display for ErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Settings for tests of source code, where each word is a whole line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeOptions {
+    /// Number of spaces tabs are expanded to
+    pub tab_width: usize,
+    /// Whether leading indentation has to be typed rather than being skipped
+    pub require_indent: bool,
+}
+
+/// A typing test, which is advanced by passing it key events
 #[derive(Debug)]
 pub struct Test {
+    /// Words of the test in order
     pub words: Vec<TestWord>,
+    /// Index of the word being typed
     pub current_word: usize,
+    /// Whether the test is over, either by typing every word or by failing it
     pub complete: bool,
+    /// Whether mistyped words can be returned to after moving past them
     pub backtracking_enabled: bool,
+    /// Whether the test restarts on the first mistake
     pub sudden_death_enabled: bool,
+    /// Whether typed characters can be deleted
     pub backspace_enabled: bool,
+    /// How mistakes are handled
+    pub error_policy: ErrorPolicy,
+    /// Time after the first keypress at which the test ends, if it's timed
+    pub time_limit: Option<Duration>,
+    /// When the first key was pressed
+    pub start: Option<Instant>,
+    /// Settings for source code tests, if the words are lines of code
+    pub code: Option<CodeOptions>,
+    /// Opponent to race, shown as a second cursor in the prompt
+    pub ghost: Option<Ghost>,
+    /// Layout emulated by remapping typed characters, if any
+    pub layout: Option<Layout>,
 }
 
 impl Test {
+    /// Create a test of the given words
     pub fn new(
         words: Vec<String>,
         backtracking_enabled: bool,
         sudden_death_enabled: bool,
         backspace_enabled: bool,
+        time_limit: Option<Duration>,
     ) -> Self {
         Self {
             words: words.into_iter().map(TestWord::from).collect(),
@@ -70,109 +224,570 @@ impl Test {
             backtracking_enabled,
             sudden_death_enabled,
             backspace_enabled,
+            error_policy: ErrorPolicy::default(),
+            time_limit,
+            start: None,
+            code: None,
+            ghost: None,
+            layout: None,
+        }
+    }
+
+    /// Treat each word as a line of source code, which keeps its spaces and is submitted with
+    /// enter. Tabs are expanded, blank lines are skipped and, unless required, so is indentation.
+    pub fn with_code(mut self, options: CodeOptions) -> Self {
+        while self.words.last().is_some_and(|w| w.text.trim().is_empty()) {
+            self.words.pop();
+        }
+        let leading_blank = self
+            .words
+            .iter()
+            .take_while(|w| w.text.trim().is_empty())
+            .count();
+        self.words.drain(..leading_blank);
+
+        for word in &mut self.words {
+            let line = expand_tabs(word.text.trim_end(), options.tab_width);
+            if options.require_indent || line.trim().is_empty() {
+                word.text = line.trim_end().to_string();
+            } else {
+                let text = line.trim_start();
+                word.indent = line.chars().count() - text.chars().count();
+                word.text = text.to_string();
+            }
         }
+
+        self.code = Some(options);
+        self
     }
 
+    /// Handle mistakes with the given policy
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Race the given ghost, whose position is shown in the prompt
+    pub fn with_ghost(mut self, ghost: Ghost) -> Self {
+        self.ghost = Some(ghost);
+        self
+    }
+
+    /// Emulate a keyboard layout, remapping typed characters before they're compared with the
+    /// prompt. Keypresses are recorded as the remapped characters.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Key event as it's typed in the emulated layout, if any
+    pub fn remap(&self, key: KeyEvent) -> KeyEvent {
+        match &self.layout {
+            Some(layout) => layout.remap(key),
+            None => key,
+        }
+    }
+
+    /// Instant at which a timed test runs out, once the first key has been pressed
+    pub fn deadline(&self) -> Option<Instant> {
+        Some(self.start? + self.time_limit?)
+    }
+
+    /// Wall time spent on the test so far, capped at the time limit
+    pub fn elapsed(&self) -> Option<Duration> {
+        let elapsed = self.start?.elapsed();
+        Some(match self.time_limit {
+            Some(limit) => elapsed.min(limit),
+            None => elapsed,
+        })
+    }
+
+    /// Append words to the end of the prompt, e.g. as a timed test drains it
+    pub fn extend(&mut self, words: impl IntoIterator<Item = String>) {
+        self.words.extend(words.into_iter().map(TestWord::from));
+    }
+
+    /// Character the next correct keypress would type, if it is a printable one
+    pub fn next_char(&self) -> Option<char> {
+        let word = self.words.get(self.current_word)?;
+        match word.next_grapheme() {
+            Some(grapheme) => grapheme.chars().next(),
+            None if self.code.is_none() && self.current_word + 1 < self.words.len() => Some(' '),
+            None => None,
+        }
+    }
+
+    /// Advance the test by a key event, ignoring events other than presses
     pub fn handle_key(&mut self, key: KeyEvent) {
-        if key.kind != KeyEventKind::Press {
+        self.handle_key_at(key, Instant::now());
+    }
+
+    /// Advance the test by a key event pressed at the given time, e.g. when replaying a recording.
+    /// Characters typed with CONTROL or ALT are shortcuts rather than text, and are ignored.
+    pub fn handle_key_at(&mut self, key: KeyEvent, time: Instant) {
+        let shortcut = key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if key.kind != KeyEventKind::Press || (shortcut && matches!(key.code, KeyCode::Char(_))) {
             return;
         }
+        let key = self.remap(key);
+
+        if self.start.is_none() {
+            self.start = Some(time);
+        }
 
         let word = &mut self.words[self.current_word];
         match key.code {
-            KeyCode::Char(' ') | KeyCode::Enter => {
-                if word.text.chars().nth(word.progress.len()) == Some(' ') {
+            KeyCode::Enter if self.code.is_some() && !word.progress.is_empty() => {
+                self.submit_word(key, time);
+            }
+            KeyCode::Tab if self.code.is_some() => {
+                let tab_width = self.code.map_or(1, |code| code.tab_width.max(1));
+                let column = word.indent + word.progress.chars().count();
+                let before = word.progress.clone();
+                word.progress
+                    .extend(std::iter::repeat_n(' ', tab_width - column % tab_width));
+                self.push_char_event(before, key, time);
+            }
+            KeyCode::Char(' ') | KeyCode::Enter if self.code.is_none() => {
+                if word.next_grapheme() == Some(" ") {
                     word.progress.push(' ');
                     word.events.push(TestEvent {
-                        time: Instant::now(),
+                        time,
                         correct: Some(true),
                         key,
                     })
                 } else if !word.progress.is_empty() || word.text.is_empty() {
-                    let correct = word.text == word.progress;
-                    if self.sudden_death_enabled && !correct {
-                        self.reset();
-                    } else {
-                        word.events.push(TestEvent {
-                            time: Instant::now(),
-                            correct: Some(correct),
-                            key,
-                        });
-                        self.next_word();
-                    }
+                    self.submit_word(key, time);
                 }
             }
+            KeyCode::Backspace if self.error_policy == ErrorPolicy::Confidence => {
+                // the attempt is recorded, but nothing is deleted
+                word.events.push(TestEvent {
+                    time,
+                    correct: None,
+                    key,
+                });
+            }
             KeyCode::Backspace => {
                 if word.progress.is_empty() && self.backtracking_enabled && self.backspace_enabled {
                     self.last_word();
                 } else if self.backspace_enabled {
                     word.events.push(TestEvent {
-                        time: Instant::now(),
-                        correct: Some(!word.text.starts_with(&word.progress[..])),
+                        time,
+                        correct: Some(!is_typed_so_far(&word.text, &word.progress)),
                         key,
                     });
-                    word.progress.pop();
+                    // the whole grapheme is deleted, like an accented letter with its accent
+                    if let Some((start, _)) = word.progress.grapheme_indices(true).next_back() {
+                        word.progress.truncate(start);
+                    }
                 }
             }
-            // CTRL-BackSpace and CTRL-W
-            KeyCode::Char('h') | KeyCode::Char('w')
-                if key.modifiers.contains(KeyModifiers::CONTROL) =>
-            {
-                if self.words[self.current_word].progress.is_empty() {
-                    self.last_word();
-                }
-
-                let word = &mut self.words[self.current_word];
-
-                word.events.push(TestEvent {
-                    time: Instant::now(),
-                    correct: None,
-                    key,
-                });
-                word.progress.clear();
-            }
             KeyCode::Char(c) => {
+                let before = word.progress.clone();
                 word.progress.push(c);
-                let correct = word.text.starts_with(&word.progress[..]);
-                if self.sudden_death_enabled && !correct {
-                    self.reset();
-                } else {
-                    word.events.push(TestEvent {
-                        time: Instant::now(),
-                        correct: Some(correct),
-                        key,
-                    });
-                    if word.progress == word.text && self.current_word == self.words.len() - 1 {
-                        self.complete = true;
-                        self.current_word = 0;
-                    }
-                }
+                // combining marks compose with the letter before them, as they do in the text
+                word.progress = word.progress.nfc().collect();
+                self.push_char_event(before, key, time);
             }
             _ => {}
         };
     }
 
+    /// Delete the word being typed, or the last word if nothing has been typed of this one, by a
+    /// key bound to [`Action::DeleteWord`](crate::keys::Action::DeleteWord)
+    pub fn delete_word(&mut self, key: KeyEvent) {
+        self.delete_word_at(key, Instant::now());
+    }
+
+    /// Delete the word being typed by a key pressed at the given time
+    pub fn delete_word_at(&mut self, key: KeyEvent, time: Instant) {
+        if self.start.is_none() {
+            self.start = Some(time);
+        }
+        // in confidence mode the attempt is recorded, but nothing is deleted
+        let confidence = self.error_policy == ErrorPolicy::Confidence;
+        if self.words[self.current_word].progress.is_empty() && !confidence {
+            self.last_word();
+        }
+
+        let word = &mut self.words[self.current_word];
+        word.events.push(TestEvent {
+            time,
+            correct: None,
+            key,
+        });
+        if !confidence {
+            word.progress.clear();
+        }
+    }
+
+    /// Record a keypress which added to the current word's progress, given the progress before it
+    fn push_char_event(&mut self, before: String, key: KeyEvent, time: Instant) {
+        let word = &mut self.words[self.current_word];
+        let correct = is_typed_so_far(&word.text, &word.progress);
+        if self.sudden_death_enabled && !correct {
+            self.reset();
+        } else {
+            if !correct && self.error_policy == ErrorPolicy::StopOnLetter {
+                word.progress = before;
+            }
+            word.events.push(TestEvent {
+                time,
+                correct: Some(correct),
+                key,
+            });
+            if word.progress == word.text && self.current_word == self.words.len() - 1 {
+                self.complete = true;
+                self.current_word = 0;
+            }
+        }
+    }
+
+    /// Finish the current word and move on to the next
+    fn submit_word(&mut self, key: KeyEvent, time: Instant) {
+        let word = &mut self.words[self.current_word];
+        let correct = word.text == word.progress;
+        let stops = matches!(
+            self.error_policy,
+            ErrorPolicy::StopOnLetter | ErrorPolicy::StopOnWord
+        );
+        if self.sudden_death_enabled && !correct {
+            self.reset();
+        } else {
+            word.events.push(TestEvent {
+                time,
+                correct: Some(correct),
+                key,
+            });
+            if correct || !stops {
+                self.next_word();
+            }
+        }
+    }
+
+    /// Whether the word at the given index is skipped over, like blank lines of code
+    fn is_skipped(&self, index: usize) -> bool {
+        self.code.is_some() && self.words[index].text.is_empty()
+    }
+
     fn last_word(&mut self) {
-        if self.current_word != 0 {
+        while self.current_word != 0 {
             self.current_word -= 1;
+            if !self.is_skipped(self.current_word) {
+                break;
+            }
         }
     }
 
     fn next_word(&mut self) {
-        if self.current_word == self.words.len() - 1 {
-            self.complete = true;
-            self.current_word = 0;
-        } else {
+        loop {
+            if self.current_word == self.words.len() - 1 {
+                self.complete = true;
+                self.current_word = 0;
+                break;
+            }
+
             self.current_word += 1;
+            if !self.is_skipped(self.current_word) {
+                break;
+            }
         }
     }
 
-    fn reset(&mut self) {
+    /// Clear the progress of every word, so the test can be taken again on the same text
+    pub fn reset(&mut self) {
         self.words.iter_mut().for_each(|word: &mut TestWord| {
             word.progress.clear();
             word.events.clear();
         });
         self.current_word = 0;
         self.complete = false;
+        self.start = None;
+    }
+}
+
+/// Replace tabs with spaces up to the next tab stop
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let width = tab_width - column % tab_width;
+            expanded.extend(std::iter::repeat_n(' ', width));
+            column += width;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use results::{Fraction, Results};
+
+    fn press(test: &mut Test, code: KeyCode) {
+        test.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn type_str(test: &mut Test, text: &str) {
+        text.chars().for_each(|c| press(test, KeyCode::Char(c)));
+    }
+
+    #[test]
+    fn ignores_shortcut_characters() {
+        let mut test = Test::new(vec!["cx".into()], true, false, true, None);
+        test.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        test.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT));
+        assert!(test.start.is_none());
+        assert_eq!(test.words[0].progress, "");
+        assert!(test.words[0].events.is_empty());
+
+        type_str(&mut test, "c");
+        assert_eq!(test.words[0].progress, "c");
+        assert_eq!(test.words[0].events.len(), 1);
+    }
+
+    fn code_test(lines: &[&str], require_indent: bool) -> Test {
+        Test::new(
+            lines.iter().map(|l| l.to_string()).collect(),
+            true,
+            false,
+            true,
+            None,
+        )
+        .with_code(CodeOptions {
+            tab_width: 4,
+            require_indent,
+        })
+    }
+
+    #[test]
+    fn code_skips_indentation_and_blank_lines() {
+        let mut test = code_test(&["", "fn main() {", "\tlet x = 1;", "", "}", ""], false);
+        assert_eq!(test.words.len(), 4);
+        assert_eq!(test.words[1].text, "let x = 1;");
+        assert_eq!(test.words[1].indent, 4);
+
+        type_str(&mut test, "fn main() {");
+        press(&mut test, KeyCode::Enter);
+        type_str(&mut test, "let x = 1;");
+        press(&mut test, KeyCode::Enter);
+        assert_eq!(test.current_word, 3);
+
+        type_str(&mut test, "}");
+        assert!(test.complete);
+    }
+
+    #[test]
+    fn code_requires_indentation() {
+        let mut test = code_test(&["if x {", "\ty", "}"], true);
+        assert_eq!(test.words[1].text, "    y");
+
+        type_str(&mut test, "if x {");
+        press(&mut test, KeyCode::Enter);
+        press(&mut test, KeyCode::Tab);
+        assert_eq!(test.words[1].progress, "    ");
+        assert_eq!(test.words[1].events[0].correct, Some(true));
+    }
+
+    #[test]
+    fn resets_to_the_same_text() {
+        let mut test = Test::new(vec!["ab".into(), "cd".into()], true, false, true, None);
+        type_str(&mut test, "ab cd");
+        assert!(test.complete);
+
+        test.reset();
+        assert!(!test.complete);
+        assert_eq!(test.start, None);
+        assert_eq!(test.current_word, 0);
+        assert!(test
+            .words
+            .iter()
+            .all(|word| word.progress.is_empty() && word.events.is_empty()));
+        type_str(&mut test, "ab cd");
+        assert!(test.complete);
+    }
+
+    #[test]
+    fn emulates_layouts() {
+        let dvorak: Layout = toml::from_str(
+            r#"
+rows = ["`1234567890[]", "',.pyfgcrl/=\\", "aoeuidhtns-", ";qjkxbmwvz"]
+shifted = ["~!@#$%^&*(){}", "\"<>PYFGCRL?+|", "AOEUIDHTNS_", ":QJKXBMWVZ"]
+"#,
+        )
+        .unwrap();
+        let mut test = Test::new(vec!["hello".into()], true, false, true, None).with_layout(dvorak);
+
+        type_str(&mut test, "jdpps");
+        assert_eq!(test.words[0].progress, "hello");
+        assert_eq!(test.words[0].events[0].key.code, KeyCode::Char('h'));
+        assert!(test.complete);
+    }
+
+    fn policy_test(policy: ErrorPolicy) -> Test {
+        Test::new(vec!["ab".into(), "cd".into()], true, false, true, None).with_error_policy(policy)
+    }
+
+    fn scores(word: &TestWord) -> Vec<Option<bool>> {
+        word.events.iter().map(|event| event.correct).collect()
+    }
+
+    #[test]
+    fn stops_on_wrong_letters() {
+        let mut test = policy_test(ErrorPolicy::StopOnLetter);
+        type_str(&mut test, "axb c");
+        assert_eq!(test.words[0].progress, "ab");
+        assert_eq!(test.current_word, 1);
+        assert_eq!(
+            scores(&test.words[0]),
+            [Some(true), Some(false), Some(true), Some(true)]
+        );
+
+        // submitting early is a wrong letter too
+        type_str(&mut test, " d");
+        assert_eq!(test.words[1].progress, "cd");
+        assert!(test.complete);
+        assert_eq!(Results::from(&test).accuracy.overall, Fraction::new(5, 7));
+    }
+
+    #[test]
+    fn stops_on_wrong_words() {
+        let mut test = policy_test(ErrorPolicy::StopOnWord);
+        type_str(&mut test, "ax ");
+        assert_eq!(test.current_word, 0);
+        assert_eq!(
+            scores(&test.words[0]),
+            [Some(true), Some(false), Some(false)]
+        );
+
+        press(&mut test, KeyCode::Backspace);
+        type_str(&mut test, "b cd");
+        assert!(test.complete);
+        assert_eq!(Results::from(&test).missed_words, ["ab"]);
+    }
+
+    #[test]
+    fn confidence_records_corrections() {
+        let mut test = policy_test(ErrorPolicy::Confidence);
+        type_str(&mut test, "ax");
+        press(&mut test, KeyCode::Backspace);
+        test.delete_word(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(test.words[0].progress, "ax");
+        assert_eq!(
+            scores(&test.words[0]),
+            [Some(true), Some(false), None, None]
+        );
+
+        type_str(&mut test, " cd");
+        assert!(test.complete);
+        assert_eq!(Results::from(&test).accuracy.overall, Fraction::new(3, 5));
+    }
+
+    #[test]
+    fn parses_error_policies() {
+        for policy in ErrorPolicy::ALL {
+            assert_eq!(policy.to_string().parse(), Ok(policy));
+        }
+        assert!("stop-on-line".parse::<ErrorPolicy>().is_err());
+    }
+
+    #[test]
+    fn normalizes_typed_text() {
+        // the text is decomposed, and accents are typed both precomposed and combining
+        let mut test = Test::new(
+            vec!["cafe\u{301}".into(), "cafe\u{301}".into()],
+            true,
+            false,
+            true,
+            None,
+        );
+        assert_eq!(test.words[0].text, "caf\u{e9}");
+
+        type_str(&mut test, "caf\u{e9} cafe\u{301}");
+        assert_eq!(test.words[1].progress, "caf\u{e9}");
+        assert!(test.complete);
+        // the bare letter is wrong until its accent is typed
+        assert_eq!(
+            scores(&test.words[1]),
+            [Some(true), Some(true), Some(true), Some(false), Some(true)]
+        );
+    }
+
+    #[test]
+    fn composes_partly_typed_graphemes() {
+        assert!(is_typed_so_far("국어", "구"));
+        assert!(is_typed_so_far("नमस्ते", "नमस्त"));
+        // a letter with one of its two accents
+        assert!(is_typed_so_far("\u{1ec7}", "\u{1eb9}"));
+        assert!(!is_typed_so_far("déjà", "de"));
+        assert!(!is_typed_so_far("déjà", "dèj"));
+        assert!(!is_typed_so_far("국어", "구어"));
+    }
+
+    #[test]
+    fn stops_on_unaccented_letters() {
+        let mut test = Test::new(vec!["déjà".into()], true, false, true, None)
+            .with_error_policy(ErrorPolicy::StopOnLetter);
+        type_str(&mut test, "dej");
+        assert_eq!(test.words[0].progress, "d");
+        assert_eq!(
+            scores(&test.words[0]),
+            [Some(true), Some(false), Some(false)]
+        );
+
+        type_str(&mut test, "éjà");
+        assert!(test.complete);
+    }
+
+    #[test]
+    fn types_multibyte_languages() {
+        let mut test = Test::new(vec!["नमस्ते".into(), "안녕".into()], true, false, true, None);
+        assert_eq!(test.next_char(), Some('न'));
+        type_str(&mut test, "नमस्ते");
+        assert_eq!(test.next_char(), Some(' '));
+        type_str(&mut test, " 안");
+        assert_eq!(test.next_char(), Some('녕'));
+        type_str(&mut test, "녕");
+        assert!(test.complete);
+        assert_eq!(Results::from(&test).accuracy.overall, Fraction::new(9, 9));
+    }
+
+    #[test]
+    fn deletes_whole_graphemes() {
+        let mut test = Test::new(vec!["किताब".into()], true, false, true, None);
+        type_str(&mut test, "कि");
+        press(&mut test, KeyCode::Backspace);
+        assert_eq!(test.words[0].progress, "");
+
+        type_str(&mut test, "cafe\u{301}");
+        press(&mut test, KeyCode::Backspace);
+        assert_eq!(test.words[0].progress, "caf");
+    }
+
+    #[test]
+    fn types_spaces_after_multibyte_characters() {
+        let mut test = Test::new(vec!["né à".into(), "x".into()], true, false, true, None);
+        type_str(&mut test, "né ");
+        assert_eq!(test.current_word, 0);
+        assert_eq!(test.words[0].progress, "né ");
+        type_str(&mut test, "à x");
+        assert!(test.complete);
+    }
+
+    #[test]
+    fn code_types_spaces() {
+        let mut test = code_test(&["a b", "c"], false);
+        type_str(&mut test, "a b");
+        assert_eq!(test.current_word, 0);
+        press(&mut test, KeyCode::Enter);
+        assert_eq!(test.current_word, 1);
     }
 }
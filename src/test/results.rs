@@ -1,16 +1,42 @@
 use super::{is_missed_word_event, Test};
 
-use crossterm::event::KeyEvent;
-use std::collections::HashMap;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 use std::{cmp, fmt};
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Factor converting CPS (clicks per second) to WPM, counting five characters as a word
+pub const WPM_PER_CPS: f64 = 12.0;
+
+/// Words per minute so far in a test in progress. Keypresses are counted the way [`Results`]
+/// counts them, so the speed doesn't jump once the test is finished.
+pub fn live_wpm(test: &Test) -> f64 {
+    let elapsed = test.elapsed().unwrap_or_default();
+    // the first keypress starts the clock, so only the ones after it are timed
+    let keypresses = test
+        .words
+        .iter()
+        .map(|word| word.events.len())
+        .sum::<usize>()
+        .saturating_sub(1);
+    if elapsed.is_zero() {
+        0.0
+    } else {
+        keypresses as f64 / elapsed.as_secs_f64() * WPM_PER_CPS
+    }
+}
+
+/// A ratio such as correct keypresses to total keypresses
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Fraction {
+    /// Number of matching items, e.g. correct keypresses
     pub numerator: usize,
+    /// Number of items in total, e.g. all keypresses
     pub denominator: usize,
 }
 
 impl Fraction {
+    /// Create a fraction of `numerator` over `denominator`
     pub const fn new(numerator: usize, denominator: usize) -> Self {
         Self {
             numerator,
@@ -43,22 +69,158 @@ impl fmt::Display for Fraction {
     }
 }
 
+/// How quickly keys were pressed
 pub struct TimingData {
-    // Instead of storing WPM, we store CPS (clicks per second)
+    /// Keypresses per second over the whole test. Instead of storing WPM, we store CPS
+    /// (clicks per second).
     pub overall_cps: f64,
+    /// Seconds since the previous keypress, for each keypress after the first
     pub per_event: Vec<f64>,
+    /// Average seconds taken to press each key
     pub per_key: HashMap<KeyEvent, f64>,
+    /// Number of timed presses of each key, which its average in `per_key` is taken over
+    pub per_key_presses: HashMap<KeyEvent, usize>,
+}
+
+impl TimingData {
+    /// Average seconds taken to press each key, by [`key_name`]. Keys with the same name, like a
+    /// letter typed with and without alt, are averaged together weighted by their presses.
+    pub fn per_key_name(&self) -> BTreeMap<String, f64> {
+        let mut totals: BTreeMap<String, (f64, usize)> = BTreeMap::new();
+        for (key, latency) in &self.per_key {
+            let presses = self.per_key_presses.get(key).copied().unwrap_or(1);
+            let total = totals.entry(key_name(key)).or_insert((0.0, 0));
+            total.0 += latency * presses as f64;
+            total.1 += presses;
+        }
+        totals
+            .into_iter()
+            .map(|(name, (total, presses))| (name, total / presses as f64))
+            .collect()
+    }
 }
 
+// keys are serialized by name, so presses of keys with the same name are merged
+impl Serialize for TimingData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut timing = serializer.serialize_struct("TimingData", 3)?;
+        timing.serialize_field("overall_cps", &self.overall_cps)?;
+        timing.serialize_field("per_event", &self.per_event)?;
+        timing.serialize_field("per_key", &self.per_key_name())?;
+        timing.end()
+    }
+}
+
+/// How many keypresses were correct
+#[derive(Serialize)]
 pub struct AccuracyData {
+    /// Correct keypresses out of all scored keypresses
     pub overall: Fraction,
+    /// Correct presses of each key out of all its presses
+    #[serde(serialize_with = "serialize_per_key")]
     pub per_key: HashMap<KeyEvent, Fraction>,
 }
 
+impl AccuracyData {
+    /// Correct presses of each key out of all its presses, by [`key_name`]. Keys with the same
+    /// name, like a letter typed with and without alt, are added together.
+    pub fn per_key_name(&self) -> BTreeMap<String, Fraction> {
+        per_key_name(&self.per_key)
+    }
+}
+
+/// Statistics of transitions from one key to the next
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct BigramStats {
+    /// Average time taken to press the second key, in seconds
+    pub latency: f64,
+    /// How often the second key was pressed correctly
+    pub accuracy: Fraction,
+}
+
+/// Statistics of a finished test, computed with `Results::from(&test)`
+#[derive(Serialize)]
 pub struct Results {
+    /// Speed statistics
     pub timing: TimingData,
+    /// Accuracy statistics
     pub accuracy: AccuracyData,
+    /// Statistics of each pair of consecutive characters
+    #[serde(serialize_with = "serialize_per_bigram")]
+    pub bigrams: HashMap<(char, char), BigramStats>,
+    /// Words containing at least one mistake
     pub missed_words: Vec<String>,
+    /// Source of the test's text, such as a quote's author
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<String>,
+    /// Seed the test's words were generated from, so it can be reproduced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Seconds by which the test beat its ghost, negative if the ghost was faster
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ghost_margin: Option<f64>,
+}
+
+/// Human-readable name of a key, e.g. `a`, `ctrl-w` or `backspace`
+pub fn key_name(key: &KeyEvent) -> String {
+    let name = match key.code {
+        KeyCode::Char(c) => c.to_string(),
+        code => format!("{:?}", code).to_lowercase(),
+    };
+
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        format!("ctrl-{}", name)
+    } else {
+        name
+    }
+}
+
+/// Fractions of each key by name, adding together those of keys with the same name
+fn per_key_name(per_key: &HashMap<KeyEvent, Fraction>) -> BTreeMap<String, Fraction> {
+    per_key
+        .iter()
+        .fold(BTreeMap::new(), |mut map, (key, fraction)| {
+            let entry = map.entry(key_name(key)).or_insert(Fraction::new(0, 0));
+            entry.numerator += fraction.numerator;
+            entry.denominator += fraction.denominator;
+            map
+        })
+}
+
+fn serialize_per_key<S>(
+    per_key: &HashMap<KeyEvent, Fraction>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_map(per_key_name(per_key))
+}
+
+fn serialize_per_bigram<S>(
+    per_bigram: &HashMap<(char, char), BigramStats>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let by_name: BTreeMap<String, &BigramStats> = per_bigram
+        .iter()
+        .map(|((a, b), stats)| (format!("{}{}", a, b), stats))
+        .collect();
+    serializer.collect_map(by_name)
+}
+
+impl Results {
+    /// Words per minute, counting every keypress
+    pub fn raw_wpm(&self) -> f64 {
+        self.timing.overall_cps * WPM_PER_CPS
+    }
+
+    /// Words per minute, scaled by accuracy
+    pub fn adjusted_wpm(&self) -> f64 {
+        self.raw_wpm() * f64::from(self.accuracy.overall)
+    }
 }
 
 impl From<&Test> for Results {
@@ -66,10 +228,21 @@ impl From<&Test> for Results {
         let events: Vec<&super::TestEvent> =
             test.words.iter().flat_map(|w| w.events.iter()).collect();
 
+        let mut timing = calc_timing(&events);
+        // Timed tests are measured against the wall clock rather than the
+        // span between the first and last keypress, counting the same keypresses.
+        if let Some(elapsed) = test.time_limit.and(test.elapsed()) {
+            timing.overall_cps = timing.per_event.len() as f64 / elapsed.as_secs_f64();
+        }
+
         Self {
-            timing: calc_timing(&events),
+            timing,
             accuracy: calc_accuracy(&events),
+            bigrams: calc_bigrams(&events),
             missed_words: calc_missed_words(test),
+            attribution: None,
+            seed: None,
+            ghost_margin: test.ghost.as_ref().and_then(|ghost| ghost.margin(test)),
         }
     }
 }
@@ -79,6 +252,7 @@ fn calc_timing(events: &[&super::TestEvent]) -> TimingData {
         overall_cps: -1.0,
         per_event: Vec::new(),
         per_key: HashMap::new(),
+        per_key_presses: HashMap::new(),
     };
 
     // map of keys to a two-tuple (total time, clicks) for counting average
@@ -100,8 +274,12 @@ fn calc_timing(events: &[&super::TestEvent]) -> TimingData {
     }
 
     timing.per_key = keys
+        .iter()
+        .map(|(&key, &(total, count))| (key, total / count as f64))
+        .collect();
+    timing.per_key_presses = keys
         .into_iter()
-        .map(|(key, (total, count))| (key, total / count as f64))
+        .map(|(key, (_, count))| (key, count))
         .collect();
 
     timing.overall_cps = timing.per_event.len() as f64 / timing.per_event.iter().sum::<f64>();
@@ -136,6 +314,47 @@ fn calc_accuracy(events: &[&super::TestEvent]) -> AccuracyData {
     acc
 }
 
+fn calc_bigrams(events: &[&super::TestEvent]) -> HashMap<(char, char), BigramStats> {
+    // map of bigrams to their total time and accuracy
+    let mut bigrams: HashMap<(char, char), (f64, Fraction)> = HashMap::new();
+
+    for win in events.windows(2) {
+        // only count transitions from a correctly typed character to another character
+        let (KeyCode::Char(first), KeyCode::Char(second)) = (win[0].key.code, win[1].key.code)
+        else {
+            continue;
+        };
+        if win[0].correct != Some(true) || win[1].correct.is_none() {
+            continue;
+        }
+        let Some(event_dur) = win[1].time.checked_duration_since(win[0].time) else {
+            continue;
+        };
+
+        let bigram = bigrams
+            .entry((first, second))
+            .or_insert((0.0, Fraction::new(0, 0)));
+        bigram.0 += event_dur.as_secs_f64();
+        bigram.1.denominator += 1;
+        if win[1].correct == Some(true) {
+            bigram.1.numerator += 1;
+        }
+    }
+
+    bigrams
+        .into_iter()
+        .map(|(bigram, (total, accuracy))| {
+            (
+                bigram,
+                BigramStats {
+                    latency: total / accuracy.denominator as f64,
+                    accuracy,
+                },
+            )
+        })
+        .collect()
+}
+
 fn calc_missed_words(test: &Test) -> Vec<String> {
     test.words
         .iter()
@@ -143,3 +362,89 @@ fn calc_missed_words(test: &Test) -> Vec<String> {
         .map(|word| word.text.clone())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestEvent;
+    use crossterm::event::KeyModifiers;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn calculates_bigrams() {
+        let start = Instant::now();
+        let event = |ms, c, correct| TestEvent {
+            time: start + Duration::from_millis(ms),
+            key: KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            correct: Some(correct),
+        };
+        let events = [
+            event(0, 'c', true),
+            event(200, 'e', true),
+            event(300, 'c', false),
+            event(500, 'c', true),
+            event(900, 'e', false),
+        ];
+        let events: Vec<&TestEvent> = events.iter().collect();
+
+        let bigrams = calc_bigrams(&events);
+        let ce = bigrams[&('c', 'e')];
+        assert_eq!(ce.accuracy, Fraction::new(1, 2));
+        assert!((ce.latency - 0.3).abs() < 1e-9);
+        // transitions from an incorrect key aren't counted
+        assert_eq!(bigrams[&('e', 'c')].accuracy, Fraction::new(0, 1));
+        assert!(!bigrams.contains_key(&('c', 'c')));
+    }
+
+    #[test]
+    fn live_wpm_matches_results() {
+        let mut test = Test::new(
+            vec!["abc".into()],
+            true,
+            false,
+            true,
+            Some(Duration::from_secs(1)),
+        );
+        // the time limit has run out, so exactly a second has elapsed
+        let start = Instant::now() - Duration::from_secs(2);
+        for (ms, c) in [(0, 'a'), (100, 'x'), (200, 'y')] {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            test.handle_key_at(key, start + Duration::from_millis(ms));
+        }
+        let key = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        test.handle_key_at(key, start + Duration::from_millis(300));
+
+        assert_eq!(live_wpm(&test), 3.0 * WPM_PER_CPS);
+        assert_eq!(live_wpm(&test), Results::from(&test).raw_wpm());
+    }
+
+    #[test]
+    fn merges_keys_with_the_same_name() {
+        let start = Instant::now();
+        let event = |ms, modifiers, correct| TestEvent {
+            time: start + Duration::from_millis(ms),
+            key: KeyEvent::new(KeyCode::Char('A'), modifiers),
+            correct: Some(correct),
+        };
+        let events = [
+            event(0, KeyModifiers::ALT, true),
+            event(100, KeyModifiers::ALT, false),
+            event(200, KeyModifiers::ALT, true),
+            event(600, KeyModifiers::NONE, true),
+        ];
+        let events: Vec<&TestEvent> = events.iter().collect();
+
+        let accuracy = calc_accuracy(&events);
+        assert_eq!(accuracy.per_key.len(), 2);
+        assert_eq!(accuracy.per_key_name()["A"], Fraction::new(3, 4));
+
+        // two presses after 100ms and one after 400ms
+        let timing = calc_timing(&events);
+        assert!((timing.per_key_name()["A"] - 0.2).abs() < 1e-9);
+
+        let json = serde_json::to_value(&timing).unwrap();
+        assert!((json["per_key"]["A"].as_f64().unwrap() - 0.2).abs() < 1e-9);
+        let json = serde_json::to_value(&accuracy).unwrap();
+        assert_eq!(json["per_key"]["A"]["numerator"], 3);
+    }
+}
@@ -1,6 +1,8 @@
 use crate::config::Theme;
+use crate::keys::{Action, KeyBindings};
+use crate::race::{self, Player};
 
-use super::test::{results, Test, TestWord};
+use super::test::{is_typed_so_far, results, Test, TestWord};
 
 use crossterm::event::KeyCode;
 use crossterm::event::KeyEvent;
@@ -11,14 +13,60 @@ use ratatui::{
     text::{Line, Span, Text},
     widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph, Widget},
 };
-use results::Fraction;
-
-// Convert CPS to WPM (clicks per second)
-const WPM_PER_CPS: f64 = 12.0;
+use results::{Fraction, WPM_PER_CPS};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use unicode_segmentation::UnicodeSegmentation;
 
 // Width of the moving average window for the WPM chart
 const WPM_SMA_WIDTH: usize = 10;
 
+// Cells taken up by each key of the on-screen keyboard, including the gap after it
+const KEY_WIDTH: u16 = 4;
+// Indentation of each keyboard row, roughly following a physical keyboard
+const ROW_OFFSETS: [u16; 4] = [0, 6, 7, 9];
+// Position and width of the space bar
+const SPACE_OFFSET: u16 = 16;
+const SPACE_WIDTH: u16 = 23;
+
+/// Width of the on-screen keyboard, including its border
+pub const KEYBOARD_WIDTH: u16 = ROW_OFFSETS[1] + 13 * KEY_WIDTH - 1 + 2;
+/// Height of the on-screen keyboard, including its border
+pub const KEYBOARD_HEIGHT: u16 = 5 + 2;
+
+// Keyboard heatmap thresholds, as accuracy and as latency relative to the median key
+const GOOD_ACCURACY: f64 = 0.98;
+const FAIR_ACCURACY: f64 = 0.9;
+const GOOD_LATENCY: f64 = 1.1;
+const FAIR_LATENCY: f64 = 1.5;
+
+// Shifted symbols of a US keyboard and the keys that type them
+const SHIFTED_SYMBOLS: [(char, char); 21] = [
+    ('~', '`'),
+    ('!', '1'),
+    ('@', '2'),
+    ('#', '3'),
+    ('$', '4'),
+    ('%', '5'),
+    ('^', '6'),
+    ('&', '7'),
+    ('*', '8'),
+    ('(', '9'),
+    (')', '0'),
+    ('_', '-'),
+    ('+', '='),
+    ('{', '['),
+    ('}', ']'),
+    ('|', '\\'),
+    (':', ';'),
+    ('"', '\''),
+    ('<', ','),
+    ('>', '.'),
+    ('?', '/'),
+];
+
 #[derive(Clone)]
 struct SizedBlock<'a> {
     block: Block<'a>,
@@ -52,10 +100,14 @@ impl<T: UsedWidget> DrawInner<T> for SizedBlock<'_> {
     }
 }
 
+/// A widget drawn with the styles of a [`Theme`]
 pub trait ThemedWidget {
+    /// Draw the widget in the given area of the buffer
     fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme);
 }
 
+/// A [`ThemedWidget`] paired with a theme, which can be rendered like any other ratatui widget.
+/// Created by [`Theme::apply_to`].
 pub struct Themed<'t, W: ?Sized> {
     theme: &'t Theme,
     widget: W,
@@ -66,6 +118,7 @@ impl<W: ThemedWidget> Widget for Themed<'_, W> {
     }
 }
 impl Theme {
+    /// Pair a widget with this theme, so it can be passed to ratatui's `render_widget`
     pub fn apply_to<W>(&self, widget: W) -> Themed<'_, W> {
         Themed {
             theme: self,
@@ -99,8 +152,26 @@ impl ThemedWidget for &Test {
         );
         input.render(buf);
 
-        let target_lines: Vec<Line> = {
-            let words = words_to_spans(&self.words, self.current_word, theme);
+        let ghost = self.ghost.as_ref().map(|ghost| ghost.position(self));
+        let mut scroll = 0;
+        let target_lines: Vec<Line> = if self.code.is_some() {
+            // Code keeps its own line structure, scrolling to keep the current line in view
+            scroll = self
+                .current_word
+                .saturating_sub(chunks[1].height.saturating_sub(2) as usize / 3);
+
+            words_to_spans(&self.words, self.current_word, ghost, theme)
+                .into_iter()
+                .zip(&self.words)
+                .map(|(spans, word)| {
+                    let mut line =
+                        vec![Span::styled(" ".repeat(word.indent), theme.prompt_untyped)];
+                    line.extend(spans);
+                    Line::from(line)
+                })
+                .collect()
+        } else {
+            let words = words_to_spans(&self.words, self.current_word, ghost, theme);
 
             let mut lines: Vec<Line> = Vec::new();
             let mut current_line: Vec<Span> = Vec::new();
@@ -122,35 +193,102 @@ impl ThemedWidget for &Test {
 
             lines
         };
-        let target = Paragraph::new(target_lines).block(
-            Block::default()
-                .title(Span::styled("Prompt", theme.title))
-                .borders(Borders::ALL)
-                .border_type(theme.border_type)
-                .border_style(theme.prompt_border),
-        );
+        let target = Paragraph::new(target_lines)
+            .scroll((scroll as u16, 0))
+            .block(
+                Block::default()
+                    .title(Span::styled("Prompt", theme.title))
+                    .borders(Borders::ALL)
+                    .border_type(theme.border_type)
+                    .border_style(theme.prompt_border),
+            );
         target.render(chunks[1], buf);
     }
 }
 
+/// Live statistics shown while a test is in progress
+pub struct Hud<'a>(pub &'a Test);
+
+impl ThemedWidget for Hud<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let test = self.0;
+        let events = test.words.iter().flat_map(|w| w.events.iter());
+
+        let elapsed = test.elapsed().unwrap_or_default();
+        let accuracy = events
+            .filter_map(|e| e.correct)
+            .fold(Fraction::new(0, 0), |acc, correct| {
+                Fraction::new(acc.numerator + correct as usize, acc.denominator + 1)
+            });
+
+        let wpm = results::live_wpm(test);
+        let accuracy = if accuracy.denominator == 0 {
+            100.0
+        } else {
+            f64::from(accuracy) * 100.0
+        };
+        let (remaining_label, remaining) = match (test.deadline(), test.time_limit) {
+            (Some(deadline), _) => (
+                "Time Left: ",
+                format_duration(deadline.saturating_duration_since(Instant::now())),
+            ),
+            (None, Some(limit)) => ("Time Left: ", format_duration(limit)),
+            (None, None) if test.code.is_some() => (
+                "Lines Left: ",
+                (test.words.len() - test.current_word).to_string(),
+            ),
+            (None, None) => (
+                "Words Left: ",
+                (test.words.len() - test.current_word).to_string(),
+            ),
+        };
+
+        let line = Line::from(vec![
+            Span::styled("WPM: ", theme.hud_label),
+            Span::styled(format!("{:.0}", wpm), theme.hud_value),
+            Span::styled("  Accuracy: ", theme.hud_label),
+            Span::styled(format!("{:.1}%", accuracy), theme.hud_value),
+            Span::styled("  Time: ", theme.hud_label),
+            Span::styled(format_duration(elapsed), theme.hud_value),
+            Span::styled(format!("  {}", remaining_label), theme.hud_label),
+            Span::styled(remaining, theme.hud_value),
+        ]);
+
+        buf.set_style(area, theme.default);
+        buf.set_line(area.x + 1, area.y, &line, area.width.saturating_sub(1));
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Styled spans of each word, with the ghost at the given word and character if there is one
 fn words_to_spans<'a>(
     words: &'a [TestWord],
     current_word: usize,
+    ghost: Option<(usize, usize)>,
     theme: &'a Theme,
 ) -> Vec<Vec<Span<'a>>> {
+    let ghost_in = |index: usize| ghost.filter(|&(word, _)| word == index).map(|(_, c)| c);
     let mut spans = Vec::new();
 
-    for word in &words[..current_word] {
+    for (index, word) in words[..current_word].iter().enumerate() {
         let parts = split_typed_word(word);
-        spans.push(word_parts_to_spans(parts, theme));
+        spans.push(word_parts_to_spans(parts, ghost_in(index), theme));
     }
 
     let parts_current = split_current_word(&words[current_word]);
-    spans.push(word_parts_to_spans(parts_current, theme));
+    spans.push(word_parts_to_spans(
+        parts_current,
+        ghost_in(current_word),
+        theme,
+    ));
 
-    for word in &words[current_word + 1..] {
+    for (index, word) in words.iter().enumerate().skip(current_word + 1) {
         let parts = vec![(word.text.clone(), Status::Untyped)];
-        spans.push(word_parts_to_spans(parts, theme));
+        spans.push(word_parts_to_spans(parts, ghost_in(index), theme));
     }
     spans
 }
@@ -172,25 +310,29 @@ fn split_current_word(word: &TestWord) -> Vec<(String, Status)> {
     let mut cur_string = String::new();
     let mut cur_status = Status::Untyped;
 
-    let mut progress = word.progress.chars();
-    for tc in word.text.chars() {
+    // colored a grapheme at a time, so accents and combining marks keep to their letters
+    let typed = word.progress.graphemes(true).count();
+    let mut progress = word.progress.graphemes(true);
+    for (index, tg) in word.text.graphemes(true).enumerate() {
         let p = progress.next();
         let status = match p {
             None => Status::CurrentUntyped,
-            Some(c) => match c {
-                c if c == tc => Status::CurrentCorrect,
+            Some(g) => match g {
+                g if g == tg => Status::CurrentCorrect,
+                // the last grapheme typed may be partway through, like a letter before its accent
+                g if index + 1 == typed && is_typed_so_far(tg, g) => Status::CurrentCorrect,
                 _ => Status::CurrentIncorrect,
             },
         };
 
         if status == cur_status {
-            cur_string.push(tc);
+            cur_string.push_str(tg);
         } else {
             if !cur_string.is_empty() {
                 parts.push((cur_string, cur_status));
                 cur_string = String::new();
             }
-            cur_string.push(tc);
+            cur_string.push_str(tg);
             cur_status = status;
 
             // first currentuntyped is cursor
@@ -215,25 +357,25 @@ fn split_typed_word(word: &TestWord) -> Vec<(String, Status)> {
     let mut cur_string = String::new();
     let mut cur_status = Status::Untyped;
 
-    let mut progress = word.progress.chars();
-    for tc in word.text.chars() {
+    let mut progress = word.progress.graphemes(true);
+    for tg in word.text.graphemes(true) {
         let p = progress.next();
         let status = match p {
             None => Status::Untyped,
-            Some(c) => match c {
-                c if c == tc => Status::Correct,
+            Some(g) => match g {
+                g if g == tg => Status::Correct,
                 _ => Status::Incorrect,
             },
         };
 
         if status == cur_status {
-            cur_string.push(tc);
+            cur_string.push_str(tg);
         } else {
             if !cur_string.is_empty() {
                 parts.push((cur_string, cur_status));
                 cur_string = String::new();
             }
-            cur_string.push(tc);
+            cur_string.push_str(tg);
             cur_status = status;
         }
     }
@@ -248,8 +390,15 @@ fn split_typed_word(word: &TestWord) -> Vec<(String, Status)> {
     parts
 }
 
-fn word_parts_to_spans(parts: Vec<(String, Status)>, theme: &Theme) -> Vec<Span<'_>> {
+/// Styled spans of a word's parts, followed by a space. The ghost is drawn over the grapheme at
+/// its index, or the space if it has passed every grapheme.
+fn word_parts_to_spans(
+    parts: Vec<(String, Status)>,
+    ghost: Option<usize>,
+    theme: &Theme,
+) -> Vec<Span<'_>> {
     let mut spans = Vec::new();
+    let mut ghost = ghost;
     for (text, status) in parts {
         let style = match status {
             Status::Correct => theme.prompt_correct,
@@ -262,9 +411,31 @@ fn word_parts_to_spans(parts: Vec<(String, Status)>, theme: &Theme) -> Vec<Span<
             Status::Overtyped => theme.prompt_incorrect,
         };
 
-        spans.push(Span::styled(text, style));
+        let len = text.graphemes(true).count();
+        match ghost {
+            // overtyped characters aren't part of the prompt, so the ghost can't be on them
+            Some(index) if index < len && status != Status::Overtyped => {
+                let (start, at) = text.grapheme_indices(true).nth(index).unwrap();
+                let end = start + at.len();
+                spans.extend([
+                    Span::styled(text[..start].to_string(), style),
+                    Span::styled(at.to_string(), style.patch(theme.prompt_ghost)),
+                    Span::styled(text[end..].to_string(), style),
+                ]);
+                ghost = None;
+            }
+            Some(index) if status != Status::Overtyped => {
+                ghost = Some(index - len);
+                spans.push(Span::styled(text, style));
+            }
+            _ => spans.push(Span::styled(text, style)),
+        }
     }
-    spans.push(Span::styled(" ", theme.prompt_untyped));
+    let space_style = match ghost {
+        Some(_) => theme.prompt_untyped.patch(theme.prompt_ghost),
+        None => theme.prompt_untyped,
+    };
+    spans.push(Span::styled(" ", space_style));
     spans
 }
 
@@ -272,7 +443,7 @@ impl ThemedWidget for &results::Results {
     fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
         buf.set_style(area, theme.default);
 
-        // Chunks
+        // Chunks, leaving the last line for the controls
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Min(1), Constraint::Length(1)])
@@ -287,32 +458,31 @@ impl ThemedWidget for &results::Results {
             .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
             .split(res_chunks[0]);
 
-        let msg = if self.missed_words.is_empty() {
-            "Press 'q' to quit or 'r' for another test"
-        } else {
-            "Press 'q' to quit, 'r' for another test or 'p' to practice missed words"
-        };
-
-        let exit = Span::styled(msg, theme.results_restart_prompt);
-        buf.set_span(chunks[1].x, chunks[1].y, &exit, chunks[1].width);
-
         // Sections
         let mut overview_text = Text::styled("", theme.results_overview);
         overview_text.extend([
-            Line::from(format!(
-                "Adjusted WPM: {:.1}",
-                self.timing.overall_cps * WPM_PER_CPS * f64::from(self.accuracy.overall)
-            )),
+            Line::from(format!("Adjusted WPM: {:.1}", self.adjusted_wpm())),
             Line::from(format!(
                 "Accuracy: {:.1}%",
                 f64::from(self.accuracy.overall) * 100f64
             )),
-            Line::from(format!(
-                "Raw WPM: {:.1}",
-                self.timing.overall_cps * WPM_PER_CPS
-            )),
+            Line::from(format!("Raw WPM: {:.1}", self.raw_wpm())),
             Line::from(format!("Correct Keypresses: {}", self.accuracy.overall)),
         ]);
+        if let Some(attribution) = &self.attribution {
+            overview_text.extend([Line::from(format!("Quote: {}", attribution))]);
+        }
+        if let Some(seed) = self.seed {
+            overview_text.extend([Line::from(format!("Seed: {}", seed))]);
+        }
+        if let Some(margin) = self.ghost_margin {
+            let side = if margin < 0.0 { "behind" } else { "ahead of" };
+            overview_text.extend([Line::from(format!(
+                "{:.1}s {} the ghost",
+                margin.abs(),
+                side
+            ))]);
+        }
         let overview = Paragraph::new(overview_text).block(
             Block::default()
                 .title(Span::styled("Overview", theme.title))
@@ -418,6 +588,516 @@ impl ThemedWidget for &results::Results {
     }
 }
 
+/// Results view ranking the slowest keys and key transitions
+pub struct LatencyResults<'a>(pub &'a results::Results);
+
+impl ThemedWidget for LatencyResults<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        buf.set_style(area, theme.default);
+
+        // Chunks, leaving the last line for the controls
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(area);
+        let info_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(1)
+            .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+            .split(chunks[0]);
+
+        // as many lines as fit inside the borders of each list, below its blank first line
+        let rows = info_chunks[0].height.saturating_sub(3) as usize;
+
+        // keys are merged by name, so a character typed with different modifiers is one row
+        let mut slowest_keys: Vec<(String, f64)> = self
+            .0
+            .timing
+            .per_key_name()
+            .into_iter()
+            .filter(|(name, _)| name.chars().count() == 1)
+            .collect();
+        slowest_keys.sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut keys_text = Text::styled("", theme.results_worst_keys);
+        keys_text.extend(
+            slowest_keys
+                .iter()
+                .map(|(name, latency)| format!("- {} at {:.0}ms", name, latency * 1000.0))
+                .take(rows)
+                .map(Line::from),
+        );
+        let keys = Paragraph::new(keys_text).block(
+            Block::default()
+                .title(Span::styled("Slowest Keys", theme.title))
+                .borders(Borders::ALL)
+                .border_type(theme.border_type)
+                .border_style(theme.results_worst_keys_border),
+        );
+        keys.render(info_chunks[0], buf);
+
+        let mut slowest_bigrams: Vec<(&(char, char), &results::BigramStats)> =
+            self.0.bigrams.iter().collect();
+        slowest_bigrams.sort_unstable_by(|a, b| b.1.latency.total_cmp(&a.1.latency));
+
+        let mut bigrams_text = Text::styled("", theme.results_worst_keys);
+        bigrams_text.extend(
+            slowest_bigrams
+                .iter()
+                .map(|((first, second), stats)| {
+                    format!(
+                        "- \"{}{}\" at {:.0}ms, {:.1}% accuracy ({}x)",
+                        first,
+                        second,
+                        stats.latency * 1000.0,
+                        f64::from(stats.accuracy) * 100.0,
+                        stats.accuracy.denominator,
+                    )
+                })
+                .take(rows)
+                .map(Line::from),
+        );
+        let bigrams = Paragraph::new(bigrams_text).block(
+            Block::default()
+                .title(Span::styled("Slowest Transitions", theme.title))
+                .borders(Borders::ALL)
+                .border_type(theme.border_type)
+                .border_style(theme.results_worst_keys_border),
+        );
+        bigrams.render(info_chunks[1], buf);
+    }
+}
+
+/// Per-key statistic the keyboard heatmap is colored by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyboardMetric {
+    /// Share of correct presses of each key
+    #[default]
+    Accuracy,
+    /// Average time taken to press each key, relative to the median key
+    Latency,
+}
+
+impl KeyboardMetric {
+    /// The other metric
+    pub fn next(self) -> Self {
+        match self {
+            KeyboardMetric::Accuracy => KeyboardMetric::Latency,
+            KeyboardMetric::Latency => KeyboardMetric::Accuracy,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            KeyboardMetric::Accuracy => "accuracy",
+            KeyboardMetric::Latency => "latency",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Heat {
+    Good,
+    Fair,
+    Poor,
+}
+
+/// The key which types a character, ignoring shift
+fn base_key(c: char) -> char {
+    if c.is_uppercase() {
+        return c.to_lowercase().next().unwrap_or(c);
+    }
+    SHIFTED_SYMBOLS
+        .iter()
+        .find(|(shifted, _)| *shifted == c)
+        .map_or(c, |(_, key)| *key)
+}
+
+/// Classify each key by how well it was typed
+fn key_heat(results: &results::Results, metric: KeyboardMetric) -> HashMap<char, Heat> {
+    match metric {
+        KeyboardMetric::Accuracy => {
+            let mut accuracy: HashMap<char, Fraction> = HashMap::new();
+            for (key, acc) in &results.accuracy.per_key {
+                if let KeyCode::Char(c) = key.code {
+                    let entry = accuracy.entry(base_key(c)).or_insert(Fraction::new(0, 0));
+                    entry.numerator += acc.numerator;
+                    entry.denominator += acc.denominator;
+                }
+            }
+
+            accuracy
+                .into_iter()
+                .filter(|(_, acc)| acc.denominator > 0)
+                .map(|(c, acc)| {
+                    let heat = match f64::from(acc) {
+                        a if a >= GOOD_ACCURACY => Heat::Good,
+                        a if a >= FAIR_ACCURACY => Heat::Fair,
+                        _ => Heat::Poor,
+                    };
+                    (c, heat)
+                })
+                .collect()
+        }
+        KeyboardMetric::Latency => {
+            let mut latencies: HashMap<char, Vec<f64>> = HashMap::new();
+            for (key, latency) in &results.timing.per_key {
+                if let KeyCode::Char(c) = key.code {
+                    if latency.is_finite() {
+                        latencies.entry(base_key(c)).or_default().push(*latency);
+                    }
+                }
+            }
+            let averages: Vec<(char, f64)> = latencies
+                .into_iter()
+                .map(|(c, l)| (c, l.iter().sum::<f64>() / l.len() as f64))
+                .collect();
+
+            let mut sorted: Vec<f64> = averages.iter().map(|(_, l)| *l).collect();
+            sorted.sort_unstable_by(f64::total_cmp);
+            let median = match sorted.get(sorted.len() / 2) {
+                Some(median) if *median > 0.0 => *median,
+                _ => return HashMap::new(),
+            };
+
+            averages
+                .into_iter()
+                .map(|(c, latency)| {
+                    let heat = match latency / median {
+                        r if r <= GOOD_LATENCY => Heat::Good,
+                        r if r <= FAIR_LATENCY => Heat::Fair,
+                        _ => Heat::Poor,
+                    };
+                    (c, heat)
+                })
+                .collect()
+        }
+    }
+}
+
+/// An on-screen keyboard, optionally colored by per-key results and highlighting the next key
+pub struct Keyboard<'a> {
+    /// Unshifted characters of each row, from the number row down, e.g. from
+    /// [`KeyboardLayout::rows`](crate::config::KeyboardLayout::rows)
+    pub rows: [&'a str; 4],
+    /// Results to color keys by, and the statistic to color them by
+    pub heat: Option<(&'a results::Results, KeyboardMetric)>,
+    /// Key to highlight, which may be a shifted character like `A`
+    pub next_key: Option<char>,
+}
+
+impl ThemedWidget for Keyboard<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let area = Rect {
+            x: area.x + area.width.saturating_sub(KEYBOARD_WIDTH) / 2,
+            width: area.width.min(KEYBOARD_WIDTH),
+            height: area.height.min(KEYBOARD_HEIGHT),
+            ..area
+        };
+
+        let title = match self.heat {
+            Some((_, metric)) => format!("Keyboard ({})", metric.name()),
+            None => String::from("Keyboard"),
+        };
+        let block = Block::default()
+            .title(Span::styled(title, theme.title))
+            .borders(Borders::ALL)
+            .border_type(theme.border_type)
+            .border_style(theme.keyboard_border);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let heat = self
+            .heat
+            .map(|(results, metric)| key_heat(results, metric))
+            .unwrap_or_default();
+        let next_key = self.next_key.map(base_key);
+        let style = |c: char| {
+            if next_key == Some(c) {
+                return theme.keyboard_next_key;
+            }
+            match heat.get(&c) {
+                Some(Heat::Good) => theme.keyboard_good,
+                Some(Heat::Fair) => theme.keyboard_fair,
+                Some(Heat::Poor) => theme.keyboard_poor,
+                None => theme.keyboard_key,
+            }
+        };
+
+        let mut draw = |x: u16, y: u16, label: &str, style| {
+            let (x, y) = (inner.x + x, inner.y + y);
+            if x < inner.right() && y < inner.bottom() {
+                buf.set_stringn(x, y, label, (inner.right() - x) as usize, style);
+            }
+        };
+
+        for (y, (row, offset)) in self.rows.iter().zip(ROW_OFFSETS).enumerate() {
+            for (i, c) in row.chars().enumerate() {
+                draw(
+                    offset + i as u16 * KEY_WIDTH,
+                    y as u16,
+                    &format!(" {} ", c),
+                    style(c),
+                );
+            }
+        }
+        draw(
+            SPACE_OFFSET,
+            4,
+            &format!("{:^width$}", "space", width = SPACE_WIDTH as usize),
+            style(' '),
+        );
+    }
+}
+
+/// Results view showing a keyboard colored by per-key accuracy or latency
+pub struct KeyboardResults<'a> {
+    /// Results of the test
+    pub results: &'a results::Results,
+    /// Unshifted characters of each row, from the number row down
+    pub rows: [&'a str; 4],
+    /// Statistic to color keys by
+    pub metric: KeyboardMetric,
+    /// Keys bound to actions, for naming the one which switches the statistic
+    pub keys: &'a KeyBindings,
+}
+
+impl ThemedWidget for KeyboardResults<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        buf.set_style(area, theme.default);
+
+        // Chunks, leaving the last line for the controls
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(area);
+        let keyboard_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(KEYBOARD_HEIGHT),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(chunks[0]);
+
+        Keyboard {
+            rows: self.rows,
+            heat: Some((self.results, self.metric)),
+            next_key: None,
+        }
+        .render(keyboard_chunks[0], buf, theme);
+
+        let legend = match self.metric {
+            KeyboardMetric::Accuracy => [
+                format!(" {:.0}%+ ", GOOD_ACCURACY * 100.0),
+                format!(" {:.0}%+ ", FAIR_ACCURACY * 100.0),
+                format!(" <{:.0}% ", FAIR_ACCURACY * 100.0),
+            ],
+            KeyboardMetric::Latency => [
+                String::from(" typical "),
+                format!(" <{}x median ", FAIR_LATENCY),
+                format!(" >{}x median ", FAIR_LATENCY),
+            ],
+        };
+        let [good, fair, poor] = legend;
+        let mut legend = Line::from(vec![
+            Span::styled(good, theme.keyboard_good),
+            Span::raw(" "),
+            Span::styled(fair, theme.keyboard_fair),
+            Span::raw(" "),
+            Span::styled(poor, theme.keyboard_poor),
+        ]);
+        if let Some(key) = self.keys.name(Action::NextMetric) {
+            legend.spans.push(Span::styled(
+                format!("  press {} to show {}", key, self.metric.next().name()),
+                theme.results_restart_prompt,
+            ));
+        }
+        let area = keyboard_chunks[1];
+        let x = area.x + area.width.saturating_sub(legend.width() as u16) / 2;
+        buf.set_line(x, area.y, &legend, area.right() - x);
+    }
+}
+
+// Width of player names on the race board and scoreboard
+const NAME_WIDTH: usize = 16;
+
+/// Live progress of every player in a race, with a bar for each
+pub struct RaceBoard<'a> {
+    /// Players in the race
+    pub players: &'a [Player],
+    /// Id of the local player, whose row is highlighted
+    pub you: usize,
+    /// Characters of progress at which a bar is full, usually the length of the prompt
+    pub length: usize,
+}
+
+impl RaceBoard<'_> {
+    /// Height needed to show every player
+    pub fn height(&self) -> u16 {
+        self.players.len() as u16 + 2
+    }
+}
+
+impl ThemedWidget for RaceBoard<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        buf.set_style(area, theme.default);
+
+        let block = Block::default()
+            .title(Span::styled("Race", theme.title))
+            .borders(Borders::ALL)
+            .border_type(theme.border_type)
+            .border_style(theme.race_border);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let bar_width = (inner.width as usize).saturating_sub(NAME_WIDTH + 10);
+        for (player, y) in self.players.iter().zip(inner.top()..inner.bottom()) {
+            let style = if player.id == self.you {
+                theme.race_text.patch(theme.race_you)
+            } else {
+                theme.race_text
+            };
+            let filled = (player.progress.chars * bar_width / self.length.max(1)).min(bar_width);
+            let wpm = player
+                .progress
+                .score
+                .map_or(player.progress.wpm, |score| score.wpm);
+
+            let line = Line::from(vec![
+                Span::styled(format!("{:<1$.1$} ", player.name, NAME_WIDTH - 1), style),
+                Span::styled("█".repeat(filled), theme.race_bar),
+                Span::styled("·".repeat(bar_width - filled), theme.race_text),
+                Span::styled(format!(" {:>5.0} WPM", wpm), style),
+            ]);
+            buf.set_line(inner.x, y, &line, inner.width);
+        }
+    }
+}
+
+/// Scores of the players who have finished a race, ranked, followed by those still racing
+pub struct Scoreboard<'a> {
+    /// Players in the race
+    pub players: &'a [Player],
+    /// Id of the local player, whose row is highlighted
+    pub you: usize,
+    /// Line of controls shown below the scoreboard
+    pub prompt: &'a str,
+}
+
+impl ThemedWidget for Scoreboard<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        buf.set_style(area, theme.default);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(area);
+
+        let mut text = Text::styled(
+            format!(
+                "    {:<w$}{:>8}{:>9}{:>10}",
+                "Player",
+                "WPM",
+                "Raw WPM",
+                "Accuracy",
+                w = NAME_WIDTH
+            ),
+            theme.race_text.patch(theme.title),
+        );
+        for (rank, player) in race::standings(self.players).into_iter().enumerate() {
+            let row = match player.progress.score {
+                Some(score) => format!(
+                    "{:<4}{:<w$.w$}{:>8.1}{:>9.1}{:>9.1}%",
+                    format!("{}.", rank + 1),
+                    player.name,
+                    score.wpm,
+                    score.raw_wpm,
+                    score.accuracy * 100.0,
+                    w = NAME_WIDTH
+                ),
+                None => format!("    {:<w$.w$}{:>8}", player.name, "racing", w = NAME_WIDTH),
+            };
+            let style = if player.id == self.you {
+                theme.race_text.patch(theme.race_you)
+            } else {
+                theme.race_text
+            };
+            text.extend([Line::styled(row, style)]);
+        }
+
+        Paragraph::new(text)
+            .block(
+                Block::default()
+                    .title(Span::styled("Scoreboard", theme.title))
+                    .borders(Borders::ALL)
+                    .border_type(theme.border_type)
+                    .border_style(theme.race_border),
+            )
+            .render(chunks[0], buf);
+
+        let prompt = Span::styled(self.prompt, theme.results_restart_prompt);
+        buf.set_span(chunks[1].x, chunks[1].y, &prompt, chunks[1].width);
+    }
+}
+
+/// Which of the results views is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResultsView {
+    /// Overall speed and accuracy, worst keys and a WPM chart, drawn by `&Results`
+    #[default]
+    Overview,
+    /// Slowest keys and transitions, drawn by [`LatencyResults`]
+    Latency,
+    /// Keyboard heatmap, drawn by [`KeyboardResults`]
+    Keyboard(KeyboardMetric),
+}
+
+impl ResultsView {
+    /// The view after this one, cycling back to the first
+    pub fn next(self) -> Self {
+        match self {
+            ResultsView::Overview => ResultsView::Latency,
+            ResultsView::Latency => ResultsView::Keyboard(KeyboardMetric::default()),
+            ResultsView::Keyboard(_) => ResultsView::Overview,
+        }
+    }
+}
+
+/// Actions offered below the results, with what each does. Practicing missed words is only
+/// offered when some were missed.
+pub fn results_controls(results: &results::Results) -> Vec<(Action, &'static str)> {
+    [
+        (Action::Quit, "to quit"),
+        (Action::Restart, "for another test"),
+        (Action::RestartSameText, "to retype the same text"),
+        (Action::Practice, "to practice missed words"),
+        (Action::Drill, "to practice weak keys"),
+        (Action::Export, "to export results"),
+        (Action::NextView, "to switch views"),
+    ]
+    .into_iter()
+    .filter(|&(action, _)| action != Action::Practice || !results.missed_words.is_empty())
+    .collect()
+}
+
+/// Line naming the keys bound to some actions and what they do, drawn below the results views,
+/// which leave their last line empty for it
+pub struct Controls<'a> {
+    /// Keys bound to actions
+    pub keys: &'a KeyBindings,
+    /// Actions to name, with what each does
+    pub actions: &'a [(Action, &'a str)],
+}
+
+impl ThemedWidget for Controls<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let prompt = Span::styled(self.keys.prompt(self.actions), theme.results_restart_prompt);
+        buf.set_span(area.x, area.y, &prompt, area.width);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -463,6 +1143,16 @@ mod tests {
                     progress: "monkeas",
                     expected: vec![("monke", Correct), ("yt", Incorrect), ("ype", Untyped)],
                 },
+                TestCase {
+                    word: "crème",
+                    progress: "creme",
+                    expected: vec![("cr", Correct), ("è", Incorrect), ("me", Correct)],
+                },
+                TestCase {
+                    word: "किताब",
+                    progress: "कताब",
+                    expected: vec![("कि", Incorrect), ("ताब", Correct)],
+                },
             ];
 
             for case in cases {
@@ -500,6 +1190,22 @@ mod tests {
                         ("pe", CurrentUntyped),
                     ],
                 },
+                TestCase {
+                    word: "한국어",
+                    // the last syllable is partway through being composed
+                    progress: "한구",
+                    expected: vec![("한국", CurrentCorrect), ("어", Cursor)],
+                },
+                TestCase {
+                    word: "déjà",
+                    progress: "dèj",
+                    expected: vec![
+                        ("d", CurrentCorrect),
+                        ("é", CurrentIncorrect),
+                        ("j", CurrentCorrect),
+                        ("à", Cursor),
+                    ],
+                },
             ];
 
             for case in cases {
@@ -509,4 +1215,102 @@ mod tests {
             }
         }
     }
+
+    mod keyboard {
+        use super::*;
+
+        #[test]
+        fn maps_shifted_characters_to_keys() {
+            assert_eq!(base_key('a'), 'a');
+            assert_eq!(base_key('A'), 'a');
+            assert_eq!(base_key('?'), '/');
+            assert_eq!(base_key('"'), '\'');
+            assert_eq!(base_key(' '), ' ');
+        }
+
+        #[test]
+        fn highlights_next_key() {
+            let theme = Theme::default();
+            let area = Rect::new(0, 0, KEYBOARD_WIDTH, KEYBOARD_HEIGHT);
+            let mut buf = Buffer::empty(area);
+            Keyboard {
+                rows: crate::config::KeyboardLayout::Dvorak.rows(),
+                heat: None,
+                next_key: Some('O'),
+            }
+            .render(area, &mut buf, &theme);
+
+            // 'o' is the second key of the dvorak home row
+            let (x, y) = (1 + ROW_OFFSETS[2] + KEY_WIDTH + 1, 3);
+            assert_eq!(buf.get(x, y).symbol(), "o");
+            assert_eq!(Some(buf.get(x, y).bg), theme.keyboard_next_key.bg);
+            assert_eq!(buf.get(x - KEY_WIDTH, y).symbol(), "a");
+            assert_eq!(Some(buf.get(x - KEY_WIDTH, y).fg), theme.keyboard_key.fg);
+        }
+    }
+    mod latency {
+        use super::*;
+        use crossterm::event::KeyModifiers;
+
+        #[test]
+        fn lists_keys_once_within_the_block() {
+            let mut test = Test::new(vec!["bacdefgh".into()], true, false, true, None);
+            let start = Instant::now();
+            for (ms, c) in (0..).step_by(100).zip("bacdefgh".chars()) {
+                let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+                test.handle_key_at(key, start + Duration::from_millis(ms));
+            }
+            let mut results = results::Results::from(&test);
+            // the same character typed with a modifier is listed with the plain one
+            let alt_a = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::ALT);
+            results.timing.per_key.insert(alt_a, 1.0);
+            results.timing.per_key_presses.insert(alt_a, 1);
+
+            let theme = Theme::default();
+            let area = Rect::new(0, 0, 80, 8);
+            let mut buf = Buffer::empty(area);
+            LatencyResults(&results).render(area, &mut buf, &theme);
+
+            let lines: Vec<String> = (0..area.height)
+                .map(|y| (0..40).map(|x| buf.get(x, y).symbol()).collect())
+                .collect();
+            // 8 rows, less the controls, margin, borders and blank first line
+            let listed: Vec<&String> = lines.iter().filter(|l| l.contains("- ")).collect();
+            assert_eq!(listed.len(), 2, "{:#?}", lines);
+            assert_eq!(listed.iter().filter(|l| l.contains("- a ")).count(), 1);
+            assert!(listed[0].contains("- a at 550ms"));
+        }
+    }
+
+    mod ghost {
+        use super::*;
+
+        fn ghost_text(parts: Vec<(String, Status)>, ghost: Option<usize>) -> Vec<String> {
+            let theme = Theme::default();
+            word_parts_to_spans(parts, ghost, &theme)
+                .into_iter()
+                .filter(|span| span.style.bg == theme.prompt_ghost.bg)
+                .map(|span| span.content.into_owned())
+                .collect()
+        }
+
+        #[test]
+        fn marks_ghost_position() {
+            let parts = || {
+                vec![
+                    ("ab".to_string(), Status::CurrentCorrect),
+                    ("cd".to_string(), Status::CurrentUntyped),
+                    ("x".to_string(), Status::Overtyped),
+                ]
+            };
+            assert_eq!(ghost_text(parts(), Some(0)), ["a"]);
+            assert_eq!(ghost_text(parts(), Some(3)), ["d"]);
+            // past the end of the word, the ghost is on the space rather than overtyped text
+            assert_eq!(ghost_text(parts(), Some(4)), [" "]);
+            assert!(ghost_text(parts(), None).is_empty());
+
+            let parts = vec![("किताब".to_string(), Status::CurrentUntyped)];
+            assert_eq!(ghost_text(parts, Some(1)), ["ता"]);
+        }
+    }
 }